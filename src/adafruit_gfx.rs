@@ -0,0 +1,61 @@
+use crate::compression::{
+    as_c_hex_array_string, c_group_defines, c_identifier, c_macro_name, fmt_c_header_preamble,
+    prefixed, with_prefix,
+};
+use crate::frame::Groups;
+use crate::layout::Layout;
+
+const USAGE: &str = "Draw a frame with Adafruit GFX, e.g. on an Adafruit_SSD1306 display:
+
+    display.clearDisplay();
+    display.drawBitmap(0, 0, bongo_frames[BONGO_IDLE_FRAMES_OFFSET], BONGO_WIDTH, BONGO_HEIGHT, SSD1306_WHITE);
+    display.display();
+";
+
+/// Generate a header of `PROGMEM` bitmaps for Adafruit GFX's `drawBitmap`, named after `prefix`
+pub fn export(
+    prefix: &str,
+    frames: &[Vec<u8>],
+    groups: &Groups,
+    num_cols: usize,
+    num_rows: usize,
+) -> String {
+    let names = groups
+        .frames()
+        .iter()
+        .map(|frame| c_identifier(&prefixed(prefix, &frame.to_string())))
+        .collect::<Vec<_>>();
+    let bitmaps = names
+        .iter()
+        .zip(frames.iter())
+        .map(|(name, frame)| {
+            format!(
+                "static const uint8_t {}[] PROGMEM = {};\n\n",
+                name,
+                as_c_hex_array_string(&Layout::RowMsbFirst.encode(frame, num_cols, num_rows))
+            )
+        })
+        .collect::<String>();
+    [
+        fmt_c_header_preamble(&with_prefix(USAGE, prefix), None),
+        format!(
+            "#define {} {}\n#define {} {}\n\n",
+            c_macro_name(&prefixed(prefix, "WIDTH")),
+            num_cols,
+            c_macro_name(&prefixed(prefix, "HEIGHT")),
+            num_rows
+        ),
+        c_group_defines(prefix, groups.iter())
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect(),
+        "\n".to_string(),
+        bitmaps,
+        format!(
+            "static const uint8_t *const {}[] = {{{}}};\n",
+            c_identifier(&prefixed(prefix, "frames")),
+            names.join(", ")
+        ),
+    ]
+    .concat()
+}
@@ -0,0 +1,237 @@
+use crate::frame::{Frame, Groups};
+use std::time::Duration;
+
+/// Tuning knobs of the typing speed driven animation
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Number of keystrokes counted as one word when computing the typing speed
+    pub chars_per_word: f64,
+    /// Time constant of the exponential decay applied to past keystrokes
+    pub wpm_decay: Duration,
+    /// Typing speed from which the cat raises its paws
+    pub prep_wpm: f64,
+    /// Typing speed from which the cat taps the bongos
+    pub tap_wpm: f64,
+    /// How long each idle frame stays on screen
+    pub idle_frame_duration: Duration,
+    /// How long a paw stays down after a keypress
+    pub tap_frame_duration: Duration,
+    /// Time without keypress after which the cat goes back to idling
+    pub idle_timeout: Duration,
+    /// Group cycled through while idling
+    pub idle_group: String,
+    /// Group shown when typing slowly
+    pub prep_group: String,
+    /// Group alternated between on every keypress when typing fast
+    pub tap_group: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            chars_per_word: 5.,
+            wpm_decay: Duration::from_secs(2),
+            prep_wpm: 10.,
+            tap_wpm: 30.,
+            idle_frame_duration: Duration::from_millis(200),
+            tap_frame_duration: Duration::from_millis(100),
+            idle_timeout: Duration::from_secs(1),
+            idle_group: "idle".to_string(),
+            prep_group: "prep".to_string(),
+            tap_group: "tap".to_string(),
+        }
+    }
+}
+
+/// Pick which frame to display based on the keystrokes seen so far.
+/// All timestamps are relative to an arbitrary origin and must be monotonic.
+pub struct Animator {
+    config: Config,
+    // Exponentially decayed count of keystrokes, as of `last_update`
+    activity: f64,
+    last_update: Duration,
+    last_keypress: Option<Duration>,
+    tap_index: usize,
+    idle_frames: usize,
+    tap_frames: usize,
+}
+
+impl Animator {
+    pub fn new(config: Config, groups: &Groups) -> Self {
+        let idle_frames = groups.len(&config.idle_group).max(1);
+        let tap_frames = groups.len(&config.tap_group).max(1);
+        Self {
+            config,
+            activity: 0.,
+            last_update: Duration::from_secs(0),
+            last_keypress: None,
+            tap_index: tap_frames - 1,
+            idle_frames,
+            tap_frames,
+        }
+    }
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+    fn decayed_activity(&self, now: Duration) -> f64 {
+        let elapsed = now.saturating_sub(self.last_update).as_secs_f64();
+        self.activity * (-elapsed / self.config.wpm_decay.as_secs_f64()).exp()
+    }
+    pub fn key_pressed(&mut self, now: Duration) {
+        self.activity = self.decayed_activity(now) + 1.;
+        self.last_update = now;
+        self.last_keypress = Some(now);
+        // Alternate paws on every keypress
+        self.tap_index = (self.tap_index + 1) % self.tap_frames;
+    }
+    pub fn wpm(&self, now: Duration) -> f64 {
+        let keystrokes_per_second =
+            self.decayed_activity(now) / self.config.wpm_decay.as_secs_f64();
+        keystrokes_per_second * 60. / self.config.chars_per_word
+    }
+    pub fn frame(&self, now: Duration) -> Frame {
+        let since_keypress = match self.last_keypress {
+            Some(keypress) => now.saturating_sub(keypress),
+            None => return self.idle_frame(now),
+        };
+        let wpm = self.wpm(now);
+        if since_keypress >= self.config.idle_timeout || wpm < self.config.prep_wpm {
+            self.idle_frame(now)
+        } else if wpm >= self.config.tap_wpm && since_keypress < self.config.tap_frame_duration {
+            Frame::new(&self.config.tap_group, self.tap_index)
+        } else {
+            Frame::new(&self.config.prep_group, 0)
+        }
+    }
+    fn idle_frame(&self, now: Duration) -> Frame {
+        let elapsed_frames = now.as_millis() / self.config.idle_frame_duration.as_millis().max(1);
+        Frame::new(
+            &self.config.idle_group,
+            elapsed_frames as usize % self.idle_frames,
+        )
+    }
+}
+
+/// Parse a keystroke log: one timestamp in milliseconds per line.
+/// Empty lines and lines starting with '#' are ignored.
+pub fn parse_keystroke_log(log: &str) -> Result<Vec<Duration>, std::num::ParseIntError> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.parse::<u64>().map(Duration::from_millis))
+        .collect()
+}
+
+/// Feed the keystrokes to an animator and sample the displayed frame every `tick`
+/// until the last keystroke is `config.idle_timeout` old.
+pub fn replay(
+    config: Config,
+    groups: &Groups,
+    keypresses: &[Duration],
+    tick: Duration,
+) -> Vec<(Duration, Frame, f64)> {
+    let end = keypresses.iter().max().cloned().unwrap_or_default() + config.idle_timeout;
+    let mut animator = Animator::new(config, groups);
+    let mut keypresses = keypresses.iter().peekable();
+    let mut timeline = Vec::new();
+    let mut now = Duration::from_secs(0);
+    while now <= end {
+        while let Some(keypress) = keypresses.next_if(|keypress| **keypress <= now) {
+            animator.key_pressed(*keypress);
+        }
+        timeline.push((now, animator.frame(now), animator.wpm(now)));
+        now += tick;
+    }
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bongo_groups() -> Groups {
+        Groups::new()
+            .with_group("idle", 5)
+            .with_group("prep", 1)
+            .with_group("tap", 2)
+    }
+
+    // A keystroke every 100 ms for one second
+    const LOG: &str = "# steady typing\n0\n100\n200\n300\n400\n\n500\n600\n700\n800\n900\n1000\n";
+
+    #[test]
+    fn parses_keystroke_logs() {
+        let keypresses = parse_keystroke_log("# comment\n0\n\n 250 \n1000\n").unwrap();
+        assert_eq!(
+            keypresses,
+            vec![
+                Duration::from_millis(0),
+                Duration::from_millis(250),
+                Duration::from_millis(1000)
+            ]
+        );
+        assert!(parse_keystroke_log("0\nfast\n").is_err());
+    }
+
+    #[test]
+    fn replays_frames_from_a_keystroke_log() {
+        let keypresses = parse_keystroke_log(LOG).unwrap();
+        let timeline = replay(
+            Config::default(),
+            &bongo_groups(),
+            &keypresses,
+            Duration::from_millis(100),
+        );
+        let frames = timeline
+            .iter()
+            .map(|(_, frame, _)| frame.to_string())
+            .collect::<Vec<_>>();
+        let expected = std::iter::once("idle_0")
+            .chain(std::iter::repeat_n("prep_0", 4))
+            .chain(["tap_1", "tap_0", "tap_1", "tap_0", "tap_1", "tap_0"])
+            .chain(std::iter::repeat_n("prep_0", 9))
+            .chain(std::iter::once("idle_0"))
+            .collect::<Vec<_>>();
+        assert_eq!(frames, expected);
+        assert_eq!(timeline.last().unwrap().0, Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn wpm_decays_after_the_last_keystroke() {
+        let keypresses = parse_keystroke_log(LOG).unwrap();
+        let mut animator = Animator::new(Config::default(), &bongo_groups());
+        for keypress in keypresses.iter() {
+            animator.key_pressed(*keypress);
+        }
+        let last = Duration::from_millis(1000);
+        let wpm = animator.wpm(last);
+        assert!((wpm - 52.05).abs() < 0.01, "{}", wpm);
+        // One time constant later, the typing speed has decayed by a factor of e
+        let decayed = animator.wpm(last + Config::default().wpm_decay);
+        assert!((decayed - wpm / std::f64::consts::E).abs() < 1e-9);
+        let timeline = replay(
+            Config::default(),
+            &bongo_groups(),
+            &keypresses,
+            Duration::from_millis(100),
+        );
+        assert!(timeline
+            .windows(2)
+            .filter(|window| window[0].0 >= last)
+            .all(|window| window[1].2 < window[0].2));
+    }
+
+    #[test]
+    fn idles_without_keystrokes() {
+        let animator = Animator::new(Config::default(), &bongo_groups());
+        let frame = |ms| animator.frame(Duration::from_millis(ms)).to_string();
+        assert_eq!(frame(0), "idle_0");
+        assert_eq!(frame(200), "idle_1");
+        assert_eq!(frame(999), "idle_4");
+        assert_eq!(frame(1000), "idle_0");
+        assert_eq!(animator.wpm(Duration::from_millis(1000)), 0.);
+    }
+}
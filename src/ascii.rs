@@ -0,0 +1,245 @@
+use crate::frame::Frame;
+use crate::oled;
+use std::time::Duration;
+
+const LIT: char = '#';
+const UNLIT: char = '.';
+
+/// A frame drawn as text, one line per row of pixels, `#` for lit pixels and `.`
+/// for unlit ones, below a header naming it:
+///
+/// ```text
+/// name: idle_0
+/// duration_ms: 200
+/// ..##..
+/// .#..#.
+/// ```
+///
+/// The duration is optional. Blocks are separated by blank lines.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub name: String,
+    pub duration: Option<Duration>,
+    pub num_cols: usize,
+    pub num_rows: usize,
+    pub frame: Vec<u8>,
+}
+
+impl Block {
+    /// Parse the header and rows of a block, `line_number` being the number of its first line
+    fn parse(lines: &[&str], line_number: usize) -> Result<Self, String> {
+        let invalid = |index: usize, reason: &str| {
+            format!(
+                "Invalid line {} {:?}: {}",
+                line_number + index,
+                lines[index],
+                reason
+            )
+        };
+        let (mut name, mut duration) = (None, None);
+        let mut rows = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            match line.split_once(':') {
+                Some(_) if !rows.is_empty() => {
+                    return Err(invalid(index, "expected a row of pixels"));
+                }
+                Some(("name", value)) => name = Some(value.trim().to_string()),
+                Some(("duration_ms", value)) => {
+                    let ms = value
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|e| invalid(index, &e.to_string()))?;
+                    duration = Some(Duration::from_millis(ms));
+                }
+                Some(_) => return Err(invalid(index, "expected name or duration_ms")),
+                None if line.chars().all(|c| c == LIT || c == UNLIT) => rows.push(*line),
+                None => return Err(invalid(index, "expected only # and . pixels")),
+            }
+        }
+        let name =
+            name.ok_or_else(|| format!("Missing name in the block at line {}", line_number))?;
+        let num_cols = rows.first().map_or(0, |row| row.len());
+        if num_cols == 0 {
+            return Err(format!("Frame {}: no pixels", name));
+        }
+        if let Some(row) = rows.iter().position(|row| row.len() != num_cols) {
+            return Err(format!(
+                "Frame {}: row {} is {} pixels wide, expected {}",
+                name,
+                row,
+                rows[row].len(),
+                num_cols
+            ));
+        }
+        let num_rows = rows.len();
+        let mut frame = vec![0; num_cols * num_rows.div_ceil(8)];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                oled::set_pixel(&mut frame, num_cols, x, y, c == LIT);
+            }
+        }
+        Ok(Self {
+            name,
+            duration,
+            num_cols,
+            num_rows,
+            frame,
+        })
+    }
+}
+
+impl std::fmt::Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "name: {}", self.name)?;
+        if let Some(duration) = self.duration {
+            writeln!(f, "duration_ms: {}", duration.as_millis())?;
+        }
+        for y in 0..self.num_rows {
+            let row = (0..self.num_cols)
+                .map(|x| {
+                    if oled::pixel(&self.frame, self.num_cols, x, y) {
+                        LIT
+                    } else {
+                        UNLIT
+                    }
+                })
+                .collect::<String>();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse every block of a text file, in order
+pub fn parse(text: &str) -> Result<Vec<Block>, String> {
+    let lines = text.lines().map(str::trim).collect::<Vec<_>>();
+    let mut blocks = Vec::new();
+    let mut begin = 0;
+    while begin < lines.len() {
+        if lines[begin].is_empty() {
+            begin += 1;
+            continue;
+        }
+        let end = lines[begin..]
+            .iter()
+            .position(|line| line.is_empty())
+            .map_or(lines.len(), |len| begin + len);
+        blocks.push(Block::parse(&lines[begin..end], begin + 1)?);
+        begin = end;
+    }
+    Ok(blocks)
+}
+
+/// Format blocks as a text file, separated by blank lines
+pub fn write(blocks: &[Block]) -> String {
+    blocks
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Decode a text file holding a single block into a frame of `frame_len` bytes.
+/// The block is clipped to the frame, and padded with unlit pixels.
+pub fn to_frame(text: &str, num_cols: usize, frame_len: usize) -> Result<Vec<u8>, String> {
+    let mut blocks = parse(text)?;
+    if blocks.len() != 1 {
+        return Err(format!("Expected a single frame, found {}", blocks.len()));
+    }
+    let block = blocks.remove(0);
+    let mut frame = vec![0; frame_len];
+    for y in 0..block.num_rows {
+        for x in 0..block.num_cols.min(num_cols) {
+            let on = oled::pixel(&block.frame, block.num_cols, x, y);
+            oled::set_pixel(&mut frame, num_cols, x, y, on);
+        }
+    }
+    Ok(frame)
+}
+
+/// Split `blocks` into groups by name: consecutive frames named `{group}_{index}` form
+/// a group. Returns the name and number of frames of each group, in order, once checked
+/// that every frame has the size of the first one.
+pub fn groups(blocks: &[Block]) -> Result<Vec<(String, usize)>, String> {
+    let first = blocks.first().ok_or("No frame")?;
+    let mut groups: Vec<(String, usize)> = Vec::new();
+    for block in blocks.iter() {
+        if (block.num_cols, block.num_rows) != (first.num_cols, first.num_rows) {
+            return Err(format!(
+                "Frame {}: {}x{} pixels, expected {}x{} like {}",
+                block.name,
+                block.num_cols,
+                block.num_rows,
+                first.num_cols,
+                first.num_rows,
+                first.name
+            ));
+        }
+        let frame = block.name.parse::<Frame>()?;
+        let continues = groups
+            .last()
+            .is_some_and(|(group, _)| *group == frame.group);
+        if continues {
+            groups.last_mut().expect("The group continues").1 += 1;
+        } else if groups.iter().any(|(group, _)| *group == frame.group) {
+            return Err(format!(
+                "Frame {}: the frames of group {} must follow each other",
+                block.name, frame.group
+            ));
+        } else {
+            groups.push((frame.group.clone(), 1));
+        }
+        let (group, len) = groups.last().expect("The frame was just added");
+        if frame.index != len - 1 {
+            return Err(format!(
+                "Frame {}: expected {}",
+                block.name,
+                Frame::new(group, len - 1)
+            ));
+        }
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name: &str, duration_ms: Option<u64>, rows: &[&str]) -> Block {
+        let text = format!("name: {}\n{}", name, rows.join("\n"));
+        let mut block = Block::parse(&text.lines().collect::<Vec<_>>(), 1).unwrap();
+        block.duration = duration_ms.map(Duration::from_millis);
+        block
+    }
+
+    #[test]
+    fn round_trips_blocks() {
+        let blocks = vec![
+            block("idle_0", Some(200), &["..##..", ".#..#.", "#....#"]),
+            block("idle_1", None, &["......", "......", "######"]),
+            block(
+                "tap_0",
+                Some(150),
+                &[
+                    "#.....", ".#....", "..#...", "...#..", "....#.", ".....#", "#.....", ".#....",
+                ],
+            ),
+        ];
+        assert_eq!(parse(&write(&blocks)), Ok(blocks));
+    }
+
+    #[test]
+    fn rejects_groups_split_by_another_group() {
+        let rows = ["#"];
+        let blocks = [
+            block("idle_0", None, &rows),
+            block("tap_0", None, &rows),
+            block("idle_1", None, &rows),
+        ];
+        assert!(groups(&blocks).is_err());
+        assert_eq!(
+            groups(&blocks[..2]),
+            Ok(vec![("idle".to_string(), 1), ("tap".to_string(), 1)])
+        );
+    }
+}
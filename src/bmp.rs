@@ -0,0 +1,62 @@
+use crate::layout::Layout;
+
+const FILE_HEADER_LEN: usize = 14;
+const INFO_HEADER_LEN: usize = 40;
+const PALETTE_LEN: usize = 8;
+
+/// Encode page-major frames as a monochrome BMP sprite sheet,
+/// with the frames stacked vertically in order.
+/// Lit pixels use palette index 1 (white), unlit ones index 0 (black).
+pub fn sprite_sheet(frames: &[Vec<u8>], num_cols: usize, num_rows: usize) -> Vec<u8> {
+    // Rows are padded to a multiple of 4 bytes, and stored bottom-up
+    let row_len = num_cols.div_ceil(8);
+    let stride = row_len.div_ceil(4) * 4;
+    let height = num_rows * frames.len();
+    let rows = frames
+        .iter()
+        .flat_map(|frame| {
+            Layout::RowMsbFirst
+                .encode(frame, num_cols, num_rows)
+                .chunks(row_len)
+                .map(|row| row.to_vec())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let raster = rows
+        .iter()
+        .rev()
+        .flat_map(|row| {
+            row.iter()
+                .cloned()
+                .chain(std::iter::repeat_n(0, stride - row_len))
+        })
+        .collect::<Vec<u8>>();
+    let offset = FILE_HEADER_LEN + INFO_HEADER_LEN + PALETTE_LEN;
+    let u16_le = |value: usize| (value as u16).to_le_bytes().to_vec();
+    let u32_le = |value: usize| (value as u32).to_le_bytes().to_vec();
+    [
+        b"BM".to_vec(),
+        u32_le(offset + raster.len()),
+        u32_le(0),
+        u32_le(offset),
+        u32_le(INFO_HEADER_LEN),
+        u32_le(num_cols),
+        u32_le(height),
+        // Planes, bits per pixel
+        u16_le(1),
+        u16_le(1),
+        // No compression
+        u32_le(0),
+        u32_le(raster.len()),
+        // 72 DPI, in pixels per meter
+        u32_le(2835),
+        u32_le(2835),
+        // Colors in the palette, important colors
+        u32_le(2),
+        u32_le(2),
+        // Palette as B, G, R, reserved
+        vec![0, 0, 0, 0, 255, 255, 255, 0],
+        raster,
+    ]
+    .concat()
+}
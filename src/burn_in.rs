@@ -0,0 +1,130 @@
+use crate::heatmap;
+use crate::oled;
+use crate::sequence::Sequence;
+use std::time::Duration;
+
+// Pixels lit at least this share of the time are flagged, besides the ones always lit
+const RISK_PERCENT: usize = 90;
+
+/// Share of the time each pixel is lit while the sequences are played back
+pub struct DutyCycle {
+    num_cols: usize,
+    num_rows: usize,
+    /// Row-major time each pixel is lit
+    on_time: Vec<Duration>,
+    total: Duration,
+}
+
+impl DutyCycle {
+    /// Play each of the sequences for `duration`, moving its frames by its pixel
+    /// shift if `shifted`. Sequences which end keep showing their last frame.
+    pub fn new(
+        frames: &[Vec<u8>],
+        sequences: &[Sequence],
+        num_cols: usize,
+        num_rows: usize,
+        duration: Duration,
+        shifted: bool,
+    ) -> Self {
+        let mut on_time = vec![Duration::from_secs(0); num_cols * num_rows];
+        let mut total = Duration::from_secs(0);
+        for sequence in sequences.iter() {
+            let mut steps = sequence
+                .play_for(duration)
+                .map(|step| (step.frame, step.duration))
+                .collect::<Vec<_>>();
+            let played = steps
+                .iter()
+                .map(|(_, duration)| *duration)
+                .sum::<Duration>();
+            if let Some((last, _)) = steps.last() {
+                steps.push((*last, duration.saturating_sub(played)));
+            }
+            let mut elapsed = Duration::from_secs(0);
+            for (frame, step_duration) in steps {
+                let frame_data = match sequence.pixel_shift().filter(|_| shifted) {
+                    Some(pixel_shift) => {
+                        let (dx, dy) = pixel_shift.offset(elapsed);
+                        oled::shift(&frames[frame], num_cols, dx as isize, dy as isize)
+                    }
+                    None => frames[frame].clone(),
+                };
+                for (index, time) in on_time.iter_mut().enumerate() {
+                    if oled::pixel(&frame_data, num_cols, index % num_cols, index / num_cols) {
+                        *time += step_duration;
+                    }
+                }
+                elapsed += step_duration;
+            }
+            total += duration;
+        }
+        Self {
+            num_cols,
+            num_rows,
+            on_time,
+            total,
+        }
+    }
+    /// Share of the time the pixel at `index` is lit, in percent
+    fn percent(&self, index: usize) -> usize {
+        if self.total.is_zero() {
+            return 0;
+        }
+        (self.on_time[index].as_millis() * 100 / self.total.as_millis()) as usize
+    }
+    /// Render the duty cycle of each pixel in the terminal, hotter pixels being lit longer
+    pub fn render(&self) -> String {
+        heatmap::render(self.num_cols, self.num_rows, 100, &|x, y| {
+            self.percent(y * self.num_cols + x)
+        })
+    }
+    /// Coordinates of the pixels lit all the time
+    pub fn always_lit(&self) -> Vec<(usize, usize)> {
+        self.on_time
+            .iter()
+            .enumerate()
+            .filter(|(_, time)| !self.total.is_zero() && **time >= self.total)
+            .map(|(index, _)| (index % self.num_cols, index / self.num_cols))
+            .collect()
+    }
+    /// Number of pixels lit at least `RISK_PERCENT` of the time
+    pub fn at_risk(&self) -> usize {
+        (0..self.on_time.len())
+            .filter(|index| self.percent(*index) >= RISK_PERCENT)
+            .count()
+    }
+    /// Highest duty cycle of any pixel, in percent
+    pub fn max_percent(&self) -> usize {
+        (0..self.on_time.len())
+            .map(|index| self.percent(index))
+            .max()
+            .unwrap_or(0)
+    }
+    /// Summary of the pixels at risk of burning in
+    pub fn summary(&self) -> String {
+        let always_lit = self.always_lit();
+        let bounds = match always_lit.first() {
+            Some(first) => {
+                let (min_x, max_x, min_y, max_y) = always_lit.iter().fold(
+                    (first.0, first.0, first.1, first.1),
+                    |(min_x, max_x, min_y, max_y), (x, y)| {
+                        (min_x.min(*x), max_x.max(*x), min_y.min(*y), max_y.max(*y))
+                    },
+                );
+                format!(
+                    ", within columns {}..={} and rows {}..={}",
+                    min_x, max_x, min_y, max_y
+                )
+            }
+            None => String::new(),
+        };
+        format!(
+            "{} pixel(s) always lit{}\n{} pixel(s) lit at least {}% of the time, at most {}%\n",
+            always_lit.len(),
+            bounds,
+            self.at_risk(),
+            RISK_PERCENT,
+            self.max_percent()
+        )
+    }
+}
@@ -0,0 +1,196 @@
+use crate::compression::{
+    as_c_hex_array_string, c_identifier, c_macro_name, fmt_as_c_define, fmt_as_rust_array,
+    fmt_c_header_preamble, prefixed,
+};
+use crate::font::Font;
+use crate::oled::{self, Bitmap};
+use serde::Deserialize;
+
+const BLEND_CODES: &str = "// Blend: 0 = or, 1 = replace, 2 = xor, 3 = clear";
+
+/// How the lit pixels of a layer are drawn onto a frame
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Blend {
+    /// Light the pixels lit in the layer, leaving the others untouched
+    Or,
+    /// Clear the box of the layer, then light the pixels lit in the layer
+    Replace,
+    /// Invert the pixels lit in the layer
+    Xor,
+    /// Clear the pixels lit in the layer
+    Clear,
+}
+
+impl Blend {
+    // Encoding of the blend mode in the exported metadata
+    fn code(&self) -> u8 {
+        match self {
+            Blend::Or => 0,
+            Blend::Replace => 1,
+            Blend::Xor => 2,
+            Blend::Clear => 3,
+        }
+    }
+    fn apply(&self, frame: bool, layer: bool) -> bool {
+        match self {
+            Blend::Or => frame || layer,
+            Blend::Replace => layer,
+            Blend::Xor => frame != layer,
+            Blend::Clear => frame && !layer,
+        }
+    }
+}
+
+/// What a layer draws
+pub enum Content {
+    /// Text drawn by the firmware at runtime, e.g. the typing speed. `preview` stands
+    /// for it in previews, and `len` characters are reserved for it.
+    Text { preview: String, len: usize },
+    /// A fixed image, e.g. a caps lock indicator
+    Icon(Bitmap),
+}
+
+/// An overlay drawn on top of every frame of an animation
+pub struct Layer {
+    pub name: String,
+    /// Position of the top left corner of the layer on the frame
+    pub x: usize,
+    pub y: usize,
+    pub blend: Blend,
+    pub content: Content,
+}
+
+impl Layer {
+    /// The pixels of the layer, as drawn in previews
+    fn bitmap(&self, font: &Font) -> Bitmap {
+        match &self.content {
+            Content::Text { preview, .. } => font.render(preview),
+            Content::Icon(bitmap) => bitmap.clone(),
+        }
+    }
+    /// Width and height of the box covered by the layer
+    pub fn size(&self, font: &Font) -> (usize, usize) {
+        match &self.content {
+            Content::Text { len, .. } => font.box_size(*len),
+            Content::Icon(bitmap) => (bitmap.width, bitmap.height),
+        }
+    }
+}
+
+/// A layer with its pixels rendered once, to be drawn on top of many frames
+pub struct RenderedLayer<'a> {
+    layer: &'a Layer,
+    bitmap: Bitmap,
+    size: (usize, usize),
+}
+
+impl RenderedLayer<'_> {
+    /// Draw the layer onto a frame of `num_cols` pixels wide, clipped to the frame
+    fn draw(&self, frame: &mut [u8], num_cols: usize) {
+        let (width, height) = self.size;
+        for y in 0..height {
+            for x in 0..width {
+                let (frame_x, frame_y) = (self.layer.x + x, self.layer.y + y);
+                let lit = self.layer.blend.apply(
+                    oled::pixel(frame, num_cols, frame_x, frame_y),
+                    self.bitmap.pixel(x, y),
+                );
+                oled::set_pixel(frame, num_cols, frame_x, frame_y, lit);
+            }
+        }
+    }
+}
+
+/// Render the pixels of every layer, as drawn in previews
+pub fn render<'a>(layers: &'a [Layer], font: &Font) -> Vec<RenderedLayer<'a>> {
+    layers
+        .iter()
+        .map(|layer| RenderedLayer {
+            layer,
+            bitmap: layer.bitmap(font),
+            size: layer.size(font),
+        })
+        .collect()
+}
+
+/// Draw `layers` in order on top of `frame`
+pub fn compose(frame: &[u8], num_cols: usize, layers: &[RenderedLayer]) -> Vec<u8> {
+    let mut composed = frame.to_vec();
+    for layer in layers.iter() {
+        layer.draw(&mut composed, num_cols);
+    }
+    composed
+}
+
+/// Position, size and blend mode of each layer
+fn metadata(layers: &[Layer], font: &Font) -> Vec<(String, usize)> {
+    layers
+        .iter()
+        .flat_map(|layer| {
+            let (width, height) = layer.size(font);
+            let name = |field: &str| format!("overlay_{}_{}", layer.name, field);
+            let mut fields = vec![
+                (name("x"), layer.x),
+                (name("y"), layer.y),
+                (name("width"), width),
+                (name("height"), height),
+                (name("blend"), usize::from(layer.blend.code())),
+            ];
+            if let Content::Text { len, .. } = layer.content {
+                fields.push((name("len"), len));
+            }
+            fields
+        })
+        .collect()
+}
+
+/// A C header locating each layer for the firmware to draw it, with the bitmaps of the icons
+pub fn c_header(prefix: &str, layers: &[Layer], font: &Font) -> String {
+    let icons = layers
+        .iter()
+        .filter_map(|layer| match &layer.content {
+            Content::Icon(bitmap) => Some(format!(
+                "static const uint8_t PROGMEM {}[{}] = {};\n",
+                c_identifier(&prefixed(prefix, &format!("overlay_{}", layer.name))),
+                bitmap.data.len(),
+                as_c_hex_array_string(&bitmap.data)
+            )),
+            Content::Text { .. } => None,
+        })
+        .collect::<String>();
+    [
+        fmt_c_header_preamble("", None),
+        format!("{}\n", BLEND_CODES),
+        metadata(layers, font)
+            .iter()
+            .map(|(name, value)| format!("{}\n", fmt_as_c_define(prefix, name, value)))
+            .collect(),
+        "\n".to_string(),
+        icons,
+    ]
+    .concat()
+}
+
+/// The layers' metadata and icons as Rust constants, named after `prefix`
+pub fn rust_declarations(prefix: &str, layers: &[Layer], font: &Font) -> String {
+    std::iter::once(BLEND_CODES.to_string())
+        .chain(metadata(layers, font).iter().map(|(name, value)| {
+            format!(
+                "pub const {}: usize = {};",
+                c_macro_name(&prefixed(prefix, name)),
+                value
+            )
+        }))
+        .chain(layers.iter().filter_map(|layer| match &layer.content {
+            Content::Icon(bitmap) => Some(fmt_as_rust_array(
+                prefix,
+                &format!("overlay_{}", layer.name),
+                "u8",
+                &bitmap.data,
+            )),
+            Content::Text { .. } => None,
+        }))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
@@ -1,4 +1,17 @@
+use crate::frame::Groups;
 use itertools::Itertools;
+use serde::Deserialize;
+
+/// Which frame each frame is stored as a diff against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    /// Every frame against the first one
+    #[default]
+    Star,
+    /// Every frame against its cheapest parent in a minimum spanning tree
+    Tree,
+}
 
 struct Range {
     begin: usize,
@@ -34,7 +47,7 @@ impl Diff {
                 if *o == *m {
                     None
                 } else {
-                    if let Some(mut last_range) = differing_regions.last_mut() {
+                    if let Some(last_range) = differing_regions.last_mut() {
                         if last_range.end == index {
                             last_range.end += 1;
                         } else {
@@ -61,7 +74,7 @@ impl Diff {
     }
     pub fn from_regions_and_diff(regions: &[usize], diff: &[u8]) -> Self {
         let differing_regions = regions
-            .into_iter()
+            .iter()
             .chunks(2)
             .into_iter()
             .map(move |chunk| {
@@ -74,7 +87,7 @@ impl Diff {
             .collect();
         Self {
             differing_regions,
-            diff: diff.iter().cloned().collect(),
+            diff: diff.to_vec(),
         }
     }
     pub fn reconstruct_frame(&self, original: &[u8]) -> Vec<u8> {
@@ -93,6 +106,18 @@ impl Diff {
             })
             .collect()
     }
+    /// Number of bytes taken by the diff in the compressed tables:
+    /// two 16 bit bounds per region, then the differing bytes
+    pub fn cost(&self) -> usize {
+        4 * self.differing_regions.len() + self.diff.len()
+    }
+    /// Begin and end (exclusive) of each differing region, as byte indices in the frame
+    pub fn regions(&self) -> Vec<(usize, usize)> {
+        self.differing_regions
+            .iter()
+            .map(|region| (region.begin, region.end))
+            .collect()
+    }
     fn index_differs(&self, index: usize) -> bool {
         for region in self.differing_regions.iter() {
             if region.contains(index) {
@@ -113,6 +138,13 @@ pub(super) struct CompactAnimation {
     frame_len: usize,
     original: Diff,
     all_frames: Vec<Diff>,
+    // Name, offset and number of frames of each group
+    frame_groups: Vec<(String, usize, usize)>,
+    // Name of each animation of a bundle, and index of each of its frames in `all_frames`
+    animations: Vec<(String, Vec<usize>)>,
+    // Entry each frame is a diff against: 0 for the original, k + 1 for frame k
+    parents: Vec<usize>,
+    encoding: Encoding,
 }
 
 impl CompactAnimation {
@@ -123,7 +155,7 @@ impl CompactAnimation {
         self.original
             .reconstruct_frame(&Self::construct_empty_frame(self.frame_len))
     }
-    fn from_original(original: &[u8]) -> Self {
+    fn from_original(original: &[u8], encoding: Encoding) -> Self {
         // Store original as a diff to an empty screen to save a few bytes
         let frame_len = original.len();
         let original =
@@ -132,13 +164,26 @@ impl CompactAnimation {
             frame_len,
             original,
             all_frames: Vec::new(),
+            frame_groups: Vec::new(),
+            animations: Vec::new(),
+            parents: Vec::new(),
+            encoding,
         }
     }
     fn with_frame(mut self, other_frame: &[u8]) -> Self {
         let diff = Diff::from_original_and_altered(&self.original_frame(), other_frame);
         self.all_frames.push(diff);
+        self.parents.push(0);
         self
     }
+    /// Decode frame `index`, applying the diffs along its chain of parents
+    pub fn reconstruct_frame(&self, index: usize) -> Vec<u8> {
+        let parent = match self.parents[index] {
+            0 => self.original_frame(),
+            entry => self.reconstruct_frame(entry - 1),
+        };
+        self.all_frames[index].reconstruct_frame(&parent)
+    }
     fn differing_regions_boundaries(&self) -> Vec<usize> {
         let mut index: usize = 0;
         std::iter::once({
@@ -187,109 +232,577 @@ impl CompactAnimation {
             )
             .collect()
     }
+    /// C type of the frame parents, which go up to the number of frames
+    fn frame_parents_c_type(&self) -> &'static str {
+        c_unsigned_type(self.all_frames.len())
+    }
+}
+
+/// Turn an arbitrary name into an upper case C identifier
+pub(super) fn c_macro_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Turn an arbitrary name into a lower case C identifier
+pub(super) fn c_identifier(name: &str) -> String {
+    c_macro_name(name).to_lowercase()
+}
+
+/// A C macro definition which can be overridden from the build configuration
+pub(super) fn fmt_as_c_default_define<T: std::fmt::Display>(name: &str, value: T) -> String {
+    format!("#ifndef {0}\n#    define {0} {1}\n#endif\n", name, value)
+}
+
+/// Smallest unsigned C integer type holding every value up to `max`
+pub(super) fn c_unsigned_type(max: usize) -> &'static str {
+    if max <= usize::from(u8::MAX) {
+        "uint8_t"
+    } else if max <= usize::from(u16::MAX) {
+        "uint16_t"
+    } else {
+        "uint32_t"
+    }
+}
+
+/// Number of bytes taken by one element of a C integer type
+pub(super) fn c_type_size(c_type: &str) -> usize {
+    match c_type {
+        "char" | "uint8_t" => 1,
+        "uint16_t" => 2,
+        "uint32_t" => 4,
+        _ => panic!("Unknown C type: {}", c_type),
+    }
 }
 
-fn as_c_array_string<T: std::fmt::Debug>(v: &[T]) -> String {
+pub(super) fn as_c_array_string<T: std::fmt::Debug>(v: &[T]) -> String {
     format!("{:?}", v).replace("[", "{").replace("]", "}")
 }
 
-fn fmt_as_rust_array<T: std::fmt::Debug>(name: &str, rust_type: &str, array: &[T]) -> String {
+/// Prepend a namespace to a generated name, so that several animations can be
+/// linked together. An empty prefix leaves the name untouched.
+pub(super) fn prefixed(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", prefix, name)
+    }
+}
+
+/// Namespace a code template after `prefix`. Templates spell their names with the
+/// `bongo` placeholder namespace, e.g. `bongo_render` or `BONGO_FRAME_SIZE`; the name
+/// of the tool in their banner is left as is.
+pub(super) fn with_prefix(template: &str, prefix: &str) -> String {
+    template
+        .split("bongo_light")
+        .map(|part| {
+            part.replace("BONGO", &c_macro_name(prefix))
+                .replace("bongo", &c_identifier(prefix))
+        })
+        .collect::<Vec<_>>()
+        .join("bongo_light")
+}
+
+/// `#define` of a macro named after `prefix` and `name`
+pub(super) fn fmt_as_c_define<T: std::fmt::Display>(prefix: &str, name: &str, value: T) -> String {
     format!(
-        "const {}: [{}; {}] = {:?};",
-        name,
+        "#define {} {}",
+        c_macro_name(&prefixed(prefix, name)),
+        value
+    )
+}
+
+/// `{PREFIX}_{GROUP}_FRAMES_OFFSET` and `_COUNT` macros locating each group of frames
+pub(super) fn c_group_defines<'a>(
+    prefix: &str,
+    groups: impl Iterator<Item = (&'a str, usize, usize)>,
+) -> Vec<String> {
+    groups
+        .flat_map(|(name, offset, len)| {
+            vec![
+                fmt_as_c_define(prefix, &format!("{}_FRAMES_OFFSET", name), offset),
+                fmt_as_c_define(prefix, &format!("{}_FRAMES_COUNT", name), len),
+            ]
+        })
+        .collect()
+}
+
+/// Format bytes as a C array initializer in hexadecimal, twelve per line
+pub(super) fn as_c_hex_array_string(bytes: &[u8]) -> String {
+    let lines = bytes
+        .chunks(12)
+        .map(|line| {
+            line.iter()
+                .map(|byte| format!("0x{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect::<Vec<_>>();
+    format!("{{\n    {}\n}}", lines.join(",\n    "))
+}
+
+pub(super) fn fmt_as_rust_array<T: std::fmt::Debug>(
+    prefix: &str,
+    name: &str,
+    rust_type: &str,
+    array: &[T],
+) -> String {
+    format!(
+        "pub const {}: [{}; {}] = {:?};",
+        c_macro_name(&prefixed(prefix, name)),
         rust_type,
         array.len(),
         array,
     )
 }
 
-fn fmt_as_c_array<T: std::fmt::Debug>(name: &str, c_type: &str, array: &[T]) -> String {
+pub(super) fn fmt_as_c_array<T: std::fmt::Debug>(
+    prefix: &str,
+    name: &str,
+    c_type: &str,
+    array: &[T],
+) -> String {
+    format!(
+        "static {}",
+        fmt_as_c_global_array(prefix, name, c_type, array)
+    )
+}
+
+/// Beginning of a generated C header: a banner, followed by the `usage` lines if any,
+/// an include guard named `guard` if any, and the includes for `PROGMEM` arrays
+pub(super) fn fmt_c_header_preamble(usage: &str, guard: Option<&str>) -> String {
+    let banner = if usage.is_empty() {
+        "/* Generated by bongo_light. Do not edit. */\n".to_string()
+    } else {
+        format!(
+            "/* Generated by bongo_light. Do not edit.\n *\n{} */\n",
+            usage
+                .lines()
+                .map(|line| format!(" *{}{}\n", if line.is_empty() { "" } else { " " }, line))
+                .collect::<String>()
+        )
+    };
+    let guard = guard.map_or(String::new(), |guard| {
+        format!("#ifndef {0}\n#define {0}\n", guard)
+    });
+    format!(
+        "{}\n#pragma once\n{}\n#include <stdint.h>\n\n#if defined(__AVR__)\n#    include <avr/pgmspace.h>\n#elif !defined(PROGMEM)\n#    define PROGMEM\n#endif\n\n",
+        banner, guard
+    )
+}
+
+/// Definition of an array visible from other translation units
+pub(super) fn fmt_as_c_global_array<T: std::fmt::Debug>(
+    prefix: &str,
+    name: &str,
+    c_type: &str,
+    array: &[T],
+) -> String {
     format!(
-        "static const {} PROGMEM {}[{}] = {};",
+        "const {} PROGMEM {}[{}] = {};",
         c_type,
-        name,
+        c_identifier(&prefixed(prefix, name)),
         array.len(),
         as_c_array_string(array),
     )
 }
 
-impl std::fmt::Display for CompactAnimation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "#### Rust ####")?;
-        writeln!(f, "```")?;
-        writeln!(f, "const FRAME_SIZE: usize = {};", self.frame_len)?;
-        writeln!(
-            f,
-            "{}",
+/// Declaration of an array defined by `fmt_as_c_global_array`
+pub(super) fn fmt_as_c_extern_array(prefix: &str, name: &str, c_type: &str, len: usize) -> String {
+    format!(
+        "extern const {} PROGMEM {}[{}];",
+        c_type,
+        c_identifier(&prefixed(prefix, name)),
+        len
+    )
+}
+
+impl CompactAnimation {
+    /// The compressed tables as Rust constants, named after `prefix`
+    pub fn rust_declarations(&self, prefix: &str) -> String {
+        vec![
+            format!(
+                "pub const {}: usize = {};",
+                c_macro_name(&prefixed(prefix, "FRAME_SIZE")),
+                self.frame_len
+            ),
             fmt_as_rust_array(
+                prefix,
                 "DIFF_REGIONS_BOUNDARIES",
                 "usize",
-                &self.differing_regions_boundaries()
-            )
-        )?;
-        writeln!(
-            f,
-            "{}",
-            fmt_as_rust_array("DIFF_REGIONS", "usize", &self.differing_regions())
-        )?;
-        writeln!(
-            f,
-            "{}",
+                &self.differing_regions_boundaries(),
+            ),
+            fmt_as_rust_array(prefix, "DIFF_REGIONS", "usize", &self.differing_regions()),
             fmt_as_rust_array(
+                prefix,
                 "DIFF_BYTES_BOUNDARIES",
                 "usize",
-                &self.differing_bytes_boundaries()
-            )
-        )?;
-        writeln!(
-            f,
-            "{}",
-            fmt_as_rust_array("DIFF_BYTES", "u8", &self.differing_bytes())
-        )?;
-        writeln!(f, "```")?;
-        writeln!(f, "#### C ####")?;
-        writeln!(f, "```")?;
-        writeln!(f, "#define FRAME_SIZE {}", self.frame_len)?;
-        writeln!(
-            f,
-            "{}",
-            fmt_as_c_array(
-                "diff_regions_boundaries",
+                &self.differing_bytes_boundaries(),
+            ),
+            fmt_as_rust_array(prefix, "DIFF_BYTES", "u8", &self.differing_bytes()),
+            "// Diff each frame applies to: 0 for the first diff, k + 1 for frame k".to_string(),
+            fmt_as_rust_array(prefix, "FRAME_PARENTS", "usize", &self.parents),
+            "// Name, index of the first frame and number of frames".to_string(),
+            fmt_as_rust_array(
+                prefix,
+                "FRAME_GROUPS",
+                "(&str, usize, usize)",
+                &self.frame_groups,
+            ),
+        ]
+        .into_iter()
+        .chain(self.animations.iter().map(|(name, indices)| {
+            fmt_as_rust_array(prefix, &format!("{}_FRAMES", name), "usize", indices)
+        }))
+        .map(|line| format!("{}\n", line))
+        .collect()
+    }
+    /// Name, C type and content of each compressed table
+    fn c_tables(&self) -> Vec<(String, &'static str, Vec<usize>)> {
+        vec![
+            (
+                "diff_regions_boundaries".to_string(),
                 "uint16_t",
-                &self.differing_regions_boundaries()
-            )
-        )?;
-        writeln!(
-            f,
-            "{}",
-            fmt_as_c_array("diff_regions", "uint16_t", &self.differing_regions())
-        )?;
-        writeln!(
-            f,
-            "{}",
-            fmt_as_c_array(
-                "diff_bytes_boundaries",
+                self.differing_regions_boundaries(),
+            ),
+            (
+                "diff_regions".to_string(),
+                "uint16_t",
+                self.differing_regions(),
+            ),
+            (
+                "diff_bytes_boundaries".to_string(),
                 "uint16_t",
-                &self.differing_bytes_boundaries()
+                self.differing_bytes_boundaries(),
+            ),
+            (
+                "diff_bytes".to_string(),
+                "char",
+                self.differing_bytes()
+                    .into_iter()
+                    .map(usize::from)
+                    .collect(),
+            ),
+            (
+                "frame_parents".to_string(),
+                self.frame_parents_c_type(),
+                self.parents.clone(),
+            ),
+        ]
+        .into_iter()
+        .chain(
+            self.animations
+                .iter()
+                .map(|(name, indices)| (format!("{}_frames", name), "uint16_t", indices.clone())),
+        )
+        .collect()
+    }
+    /// Index of each frame of `animation` in the compressed tables,
+    /// for animations compressed together in a bundle
+    pub fn frame_indices(&self, animation: &str) -> Option<&[usize]> {
+        self.animations
+            .iter()
+            .find(|(name, _)| name == animation)
+            .map(|(_, indices)| indices.as_slice())
+    }
+    fn c_defines(&self, prefix: &str) -> Vec<String> {
+        std::iter::once(fmt_as_c_define(prefix, "FRAME_SIZE", self.frame_len))
+            .chain(c_group_defines(
+                prefix,
+                self.frame_groups
+                    .iter()
+                    .map(|(name, offset, len)| (name.as_str(), *offset, *len)),
+            ))
+            .collect()
+    }
+    /// The compressed tables as C arrays stored in program memory,
+    /// private to the file including them
+    pub fn c_declarations(&self, prefix: &str) -> String {
+        self.c_defines(prefix)
+            .into_iter()
+            .chain(
+                self.c_tables()
+                    .iter()
+                    .map(|(name, c_type, table)| fmt_as_c_array(prefix, name, c_type, table)),
             )
-        )?;
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+    /// Macros and declarations of the compressed tables, for a header
+    pub fn c_header_declarations(&self, prefix: &str) -> String {
+        self.c_defines(prefix)
+            .into_iter()
+            .chain(self.c_tables().iter().map(|(name, c_type, table)| {
+                fmt_as_c_extern_array(prefix, name, c_type, table.len())
+            }))
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+    /// Definitions of the compressed tables declared by `c_header_declarations`
+    pub fn c_source_definitions(&self, prefix: &str) -> String {
+        self.c_tables()
+            .iter()
+            .map(|(name, c_type, table)| {
+                format!("{}\n", fmt_as_c_global_array(prefix, name, c_type, table))
+            })
+            .collect()
+    }
+    /// The compressed tables as Python constants, for CircuitPython, named after `prefix`
+    pub fn python_declarations(&self, prefix: &str) -> String {
+        let name = |name: &str| c_macro_name(&prefixed(prefix, name));
+        let groups = self
+            .frame_groups
+            .iter()
+            .map(|(name, offset, len)| format!("{:?}: ({}, {})", name, offset, len))
+            .collect::<Vec<_>>()
+            .join(", ");
+        [
+            format!("{} = {}", name("FRAME_SIZE"), self.frame_len),
+            format!(
+                "{} = array(\"H\", {:?})",
+                name("DIFF_REGIONS_BOUNDARIES"),
+                self.differing_regions_boundaries()
+            ),
+            format!(
+                "{} = array(\"H\", {:?})",
+                name("DIFF_REGIONS"),
+                self.differing_regions()
+            ),
+            format!(
+                "{} = array(\"H\", {:?})",
+                name("DIFF_BYTES_BOUNDARIES"),
+                self.differing_bytes_boundaries()
+            ),
+            format!(
+                "{} = bytes({:?})",
+                name("DIFF_BYTES"),
+                self.differing_bytes()
+            ),
+            match self.frame_parents_c_type() {
+                "uint8_t" => format!("{} = bytes({:?})", name("FRAME_PARENTS"), self.parents),
+                c_type => format!(
+                    "{} = array({:?}, {:?})",
+                    name("FRAME_PARENTS"),
+                    if c_type == "uint16_t" { "H" } else { "L" },
+                    self.parents
+                ),
+            },
+            "# Name: (index of the first frame, number of frames)".to_string(),
+            format!("{} = {{{}}}", name("FRAME_GROUPS"), groups),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+    }
+    /// Name and number of bytes of each compressed table
+    pub fn table_sizes(&self) -> Vec<(String, usize)> {
+        self.c_tables()
+            .into_iter()
+            .map(|(name, c_type, table)| (name, c_type_size(c_type) * table.len()))
+            .collect()
+    }
+    /// Number of bytes taken by the compressed tables
+    pub fn size(&self) -> usize {
+        self.table_sizes().iter().map(|(_, size)| size).sum()
+    }
+    /// Number of bytes of a decoded frame
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+    /// Which frame each frame is stored as a diff against
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+    /// Number of frames, across every animation of a bundle
+    pub fn num_frames(&self) -> usize {
+        self.all_frames.len()
+    }
+    /// Number of bytes each diff takes in the compressed tables, starting with the first diff
+    pub fn diff_costs(&self) -> Vec<usize> {
+        std::iter::once(&self.original)
+            .chain(self.all_frames.iter())
+            .map(Diff::cost)
+            .collect()
+    }
+    /// Number of regions and bytes applied to decode each frame:
+    /// those of the first diff, and of every diff along the chain of parents
+    pub fn decode_sizes(&self) -> Vec<(usize, usize)> {
+        let sizes = self.diff_sizes();
+        (0..self.all_frames.len())
+            .map(|index| {
+                let mut entry = index + 1;
+                let mut total = sizes[0];
+                while entry != 0 {
+                    total = (total.0 + sizes[entry].0, total.1 + sizes[entry].1);
+                    entry = self.parents[entry - 1];
+                }
+                total
+            })
+            .collect()
+    }
+    /// Number of differing regions and bytes of the diff of the base frame,
+    /// followed by those of the diff of each frame
+    pub fn diff_sizes(&self) -> Vec<(usize, usize)> {
+        std::iter::once(&self.original)
+            .chain(self.all_frames.iter())
+            .map(|diff| (diff.differing_regions.len(), diff.diff.len()))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for CompactAnimation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let uncompressed = self.frame_len * self.all_frames.len();
         writeln!(
             f,
-            "{}",
-            fmt_as_c_array("diff_bytes", "char", &self.differing_bytes())
+            "{} frames of {} bytes: {} bytes compressed to {} ({:.0}%)",
+            self.all_frames.len(),
+            self.frame_len,
+            uncompressed,
+            self.size(),
+            100. * self.size() as f64 / uncompressed as f64
         )?;
-        writeln!(f, "```")?;
-        let size = self.differing_bytes().len()
-            + 2 * (self.differing_bytes_boundaries().len()
-                + self.differing_regions().len()
-                + self.differing_regions_boundaries().len());
-        writeln!(f, "Total size in bytes: {}", size)
+        for (name, offset, len) in self.frame_groups.iter() {
+            writeln!(f, "  {}: frames {}..{}", name, offset, offset + len)?;
+        }
+        Ok(())
     }
 }
-pub(super) fn compress_frames(all_frames: &[Vec<u8>]) -> CompactAnimation {
-    let mut slim = CompactAnimation::from_original(&all_frames[0]);
-    for frame in all_frames {
-        slim = slim.with_frame(frame)
+
+/// Compress one or more named animations into shared tables: the first frame is
+/// the base every frame is diffed against, and all diffs share a single pool.
+///
+/// When bundling several animations, identical frames are only stored once,
+/// each named animation gets an index table mapping its frames to the pool,
+/// and group names are prefixed with the name of their animation. Group
+/// offsets are then indices in the animation's index table.
+/// A single unnamed animation keeps its frames in order, without index table.
+pub(super) fn compress_frames(animations: &[(&str, &Groups, &[Vec<u8>])]) -> CompactAnimation {
+    let bundle = animations.len() > 1;
+    let mut slim = CompactAnimation::from_original(&animations[0].2[0], Encoding::Star);
+    let mut pool: Vec<&[u8]> = Vec::new();
+    for (name, groups, frames) in animations {
+        let mut indices = Vec::new();
+        for frame in frames.iter() {
+            match pool.iter().position(|pooled| *pooled == frame.as_slice()) {
+                Some(index) if bundle => indices.push(index),
+                _ => {
+                    indices.push(pool.len());
+                    pool.push(frame);
+                    slim = slim.with_frame(frame);
+                }
+            }
+        }
+        slim.frame_groups.extend(
+            groups
+                .iter()
+                .map(|(group, offset, len)| (prefixed(name, group), offset, len)),
+        );
+        if !name.is_empty() {
+            slim.animations.push((name.to_string(), indices));
+        }
     }
     slim
 }
+
+/// Cost of the diff from each frame (row) to each other frame (column)
+pub(super) fn similarity_matrix(frames: &[Vec<u8>]) -> Vec<Vec<usize>> {
+    frames
+        .iter()
+        .map(|original| {
+            frames
+                .iter()
+                .map(|altered| Diff::from_original_and_altered(original, altered).cost())
+                .collect()
+        })
+        .collect()
+}
+
+/// Parent of each frame in the minimum spanning tree rooted at an empty screen,
+/// each edge weighing the cost of the diff between its frames.
+/// `None` stands for the empty screen.
+pub(super) fn spanning_tree(frames: &[Vec<u8>]) -> Vec<Option<usize>> {
+    let matrix = similarity_matrix(frames);
+    let empty = CompactAnimation::construct_empty_frame(frames[0].len());
+    // Cheapest known way to reach each frame not in the tree yet
+    let mut cheapest = frames
+        .iter()
+        .map(|frame| (Diff::from_original_and_altered(&empty, frame).cost(), None))
+        .collect::<Vec<_>>();
+    let mut parents = vec![None; frames.len()];
+    let mut in_tree = vec![false; frames.len()];
+    // Prim's algorithm
+    for _ in 0..frames.len() {
+        let (next, (_, parent)) = cheapest
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !in_tree[*index])
+            .min_by_key(|(_, (cost, _))| *cost)
+            .map(|(index, edge)| (index, *edge))
+            .expect("A frame is left out of the tree");
+        in_tree[next] = true;
+        parents[next] = parent;
+        for (index, edge) in cheapest.iter_mut().enumerate() {
+            if !in_tree[index] && matrix[next][index] < edge.0 {
+                *edge = (matrix[next][index], Some(next));
+            }
+        }
+    }
+    parents
+}
+
+/// Compress frames along their minimum spanning tree: each frame is stored as a
+/// diff against its cheapest parent, the first diff being left empty
+pub(super) fn compress_frames_as_tree(groups: &Groups, frames: &[Vec<u8>]) -> CompactAnimation {
+    let empty = CompactAnimation::construct_empty_frame(frames[0].len());
+    let mut slim = CompactAnimation::from_original(&empty, Encoding::Tree);
+    for (frame, parent) in frames.iter().zip(spanning_tree(frames)) {
+        let parent_frame = parent.map_or(&empty, |parent| &frames[parent]);
+        slim.all_frames
+            .push(Diff::from_original_and_altered(parent_frame, frame));
+        slim.parents.push(parent.map_or(0, |parent| parent + 1));
+    }
+    slim.frame_groups = groups
+        .iter()
+        .map(|(name, offset, len)| (name.to_string(), offset, len))
+        .collect();
+    slim
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fat_bongo;
+
+    #[test]
+    fn star_and_tree_encodings_decode_every_frame() {
+        let groups = fat_bongo::groups();
+        let frames = groups
+            .frames()
+            .iter()
+            .map(fat_bongo::get_frame)
+            .collect::<Vec<_>>();
+        let star = compress_frames(&[("", &groups, &frames)]);
+        let tree = compress_frames_as_tree(&groups, &frames);
+        for (index, frame) in frames.iter().enumerate() {
+            assert_eq!(&star.reconstruct_frame(index), frame);
+            assert_eq!(&tree.reconstruct_frame(index), frame);
+        }
+        // Every frame of the tree is reached from the empty screen without cycles
+        let parents = spanning_tree(&frames);
+        for start in 0..frames.len() {
+            let mut frame = Some(start);
+            for _ in 0..=frames.len() {
+                frame = frame.and_then(|frame| parents[frame]);
+            }
+            assert_eq!(frame, None);
+        }
+    }
+}
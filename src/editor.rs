@@ -0,0 +1,288 @@
+use crate::oled;
+use std::io::Write;
+use termion::color;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+
+const UNSAVED: &str = "Unsaved changes: press s to save, or q again to quit";
+const HELP: &str = "arrows/hjkl move | space toggle | v mark | y copy | p paste | u undo | \
+                    n/b next/previous frame | o onion skin | s save | q quit";
+
+/// Write a frame back to the project, given its index and content
+pub type SaveFrame<'a> = dyn Fn(usize, &[u8]) -> Result<(), String> + 'a;
+
+/// A rectangle of pixels, row-major
+struct Clip {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+/// Edit the pixels of a set of frames, keeping an undo history across frames
+pub struct Editor {
+    frames: Vec<Vec<u8>>,
+    names: Vec<String>,
+    num_cols: usize,
+    num_rows: usize,
+    current: usize,
+    cursor: (usize, usize),
+    /// Corner of the rectangle being selected, the other one being the cursor
+    mark: Option<(usize, usize)>,
+    clipboard: Option<Clip>,
+    /// Index and previous content of each edited frame, the latest edit last
+    history: Vec<(usize, Vec<u8>)>,
+    /// Whether each frame changed since it was last saved
+    modified: Vec<bool>,
+    /// Show the pixels of the previous frame under the current one
+    onion_skin: bool,
+    status: String,
+}
+
+impl Editor {
+    /// An editor opened on the first of `frames`, each named after the same entry of `names`
+    pub fn new(
+        frames: Vec<Vec<u8>>,
+        names: Vec<String>,
+        num_cols: usize,
+        num_rows: usize,
+    ) -> Result<Self, String> {
+        if frames.is_empty() {
+            return Err("No frames to edit".to_string());
+        }
+        let modified = vec![false; frames.len()];
+        Ok(Self {
+            frames,
+            names,
+            num_cols,
+            num_rows,
+            current: 0,
+            cursor: (0, 0),
+            mark: None,
+            clipboard: None,
+            history: Vec::new(),
+            modified,
+            onion_skin: true,
+            status: HELP.to_string(),
+        })
+    }
+    /// Bounds of the selected rectangle, inclusive
+    fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.mark.map(|(x, y)| {
+            let (cursor_x, cursor_y) = self.cursor;
+            (
+                (x.min(cursor_x), y.min(cursor_y)),
+                (x.max(cursor_x), y.max(cursor_y)),
+            )
+        })
+    }
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.saturating_add_signed(dx).min(self.num_cols - 1),
+            y.saturating_add_signed(dy).min(self.num_rows - 1),
+        );
+    }
+    /// Remember the current frame before changing it
+    fn checkpoint(&mut self) {
+        self.history
+            .push((self.current, self.frames[self.current].clone()));
+        self.modified[self.current] = true;
+    }
+    fn toggle(&mut self) {
+        self.checkpoint();
+        let (x, y) = self.cursor;
+        let frame = &mut self.frames[self.current];
+        let on = oled::pixel(frame, self.num_cols, x, y);
+        oled::set_pixel(frame, self.num_cols, x, y, !on);
+    }
+    fn copy(&mut self) {
+        let ((left, top), (right, bottom)) = self.selection().unwrap_or((self.cursor, self.cursor));
+        let (frame, num_cols) = (&self.frames[self.current], self.num_cols);
+        let pixels = (top..=bottom)
+            .flat_map(|y| (left..=right).map(move |x| oled::pixel(frame, num_cols, x, y)))
+            .collect();
+        let (width, height) = (right - left + 1, bottom - top + 1);
+        self.clipboard = Some(Clip {
+            width,
+            height,
+            pixels,
+        });
+        self.mark = None;
+        self.status = format!("Copied {}x{} pixels", width, height);
+    }
+    /// Paste the clipboard with its top left corner at the cursor, clipped to the frame
+    fn paste(&mut self) {
+        let Some(clip) = self.clipboard.take() else {
+            self.status = "Nothing to paste: copy a rectangle with y first".to_string();
+            return;
+        };
+        self.checkpoint();
+        let (left, top) = self.cursor;
+        for (index, on) in clip.pixels.iter().enumerate() {
+            let (x, y) = (left + index % clip.width, top + index / clip.width);
+            if x < self.num_cols && y < self.num_rows {
+                oled::set_pixel(&mut self.frames[self.current], self.num_cols, x, y, *on);
+            }
+        }
+        self.status = format!("Pasted {}x{} pixels", clip.width, clip.height);
+        self.clipboard = Some(clip);
+    }
+    fn undo(&mut self) {
+        match self.history.pop() {
+            Some((index, frame)) => {
+                self.frames[index] = frame;
+                self.modified[index] = true;
+                self.current = index;
+                self.status = format!("Undid an edit of {}", self.names[index]);
+            }
+            None => self.status = "Nothing to undo".to_string(),
+        }
+    }
+    fn switch_frame(&mut self, offset: isize) {
+        let len = self.frames.len() as isize;
+        self.current = (self.current as isize + offset).rem_euclid(len) as usize;
+        self.mark = None;
+    }
+    /// Save every modified frame with `save`, given its index and content
+    fn save(&mut self, save: &SaveFrame<'_>) {
+        let mut saved = 0;
+        for (index, frame) in self.frames.iter().enumerate() {
+            if !self.modified[index] {
+                continue;
+            }
+            if let Err(e) = save(index, frame) {
+                self.status = e;
+                return;
+            }
+            self.modified[index] = false;
+            saved += 1;
+        }
+        self.status = format!("Saved {} frame(s)", saved);
+    }
+    pub fn is_modified(&self) -> bool {
+        self.modified.iter().any(|modified| *modified)
+    }
+    /// Apply a key, returning false once the editor should close
+    fn handle(&mut self, key: Key, save: &SaveFrame<'_>) -> bool {
+        let status = std::mem::replace(&mut self.status, HELP.to_string());
+        match key {
+            Key::Left | Key::Char('h') => self.move_cursor(-1, 0),
+            Key::Right | Key::Char('l') => self.move_cursor(1, 0),
+            Key::Up | Key::Char('k') => self.move_cursor(0, -1),
+            Key::Down | Key::Char('j') => self.move_cursor(0, 1),
+            Key::Char(' ') => self.toggle(),
+            Key::Char('v') => {
+                self.mark = match self.mark {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                }
+            }
+            Key::Char('y') => self.copy(),
+            Key::Char('p') => self.paste(),
+            Key::Char('u') => self.undo(),
+            Key::Char('n') | Key::PageDown => self.switch_frame(1),
+            Key::Char('b') | Key::PageUp => self.switch_frame(-1),
+            Key::Char('o') => self.onion_skin = !self.onion_skin,
+            Key::Char('s') => self.save(save),
+            Key::Char('q') | Key::Esc | Key::Ctrl('c') => {
+                if !self.is_modified() || status == UNSAVED {
+                    return false;
+                }
+                self.status = UNSAVED.to_string();
+            }
+            _ => (),
+        }
+        true
+    }
+    /// Draw the current frame: lit pixels as `██`, those only lit in the previous frame
+    /// as `░░` when onion skinning, the selection on a grey background and the cursor
+    /// in yellow
+    fn render(&self) -> String {
+        let frame = &self.frames[self.current];
+        let previous = self
+            .current
+            .checked_sub(1)
+            .filter(|_| self.onion_skin)
+            .map(|index| &self.frames[index]);
+        let selection = self.selection();
+        (0..self.num_rows)
+            .map(|y| {
+                let pixels = (0..self.num_cols)
+                    .map(|x| {
+                        let selected = selection.is_some_and(|((left, top), (right, bottom))| {
+                            (left..=right).contains(&x) && (top..=bottom).contains(&y)
+                        });
+                        let background = if selected {
+                            color::Bg(color::AnsiValue::grayscale(6)).to_string()
+                        } else {
+                            color::Bg(color::Reset).to_string()
+                        };
+                        let foreground = if (x, y) == self.cursor {
+                            color::Fg(color::Yellow).to_string()
+                        } else {
+                            color::Fg(color::White).to_string()
+                        };
+                        let lit = oled::pixel(frame, self.num_cols, x, y);
+                        let glyph = match (lit, (x, y) == self.cursor) {
+                            (true, _) => "██",
+                            (false, true) => "[]",
+                            (false, false)
+                                if previous.is_some_and(|previous| {
+                                    oled::pixel(previous, self.num_cols, x, y)
+                                }) =>
+                            {
+                                "░░"
+                            }
+                            (false, false) => "  ",
+                        };
+                        background + &foreground + glyph
+                    })
+                    .collect::<String>();
+                format!(
+                    "{}{}{}",
+                    pixels,
+                    color::Bg(color::Reset),
+                    color::Fg(color::Reset)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+    /// Run the editor full screen until it is closed
+    pub fn run(&mut self, save: &SaveFrame<'_>) {
+        let mut stdout = std::io::stdout()
+            .into_raw_mode()
+            .expect("Failed to switch the terminal to raw mode");
+        let mut keys = std::io::stdin().keys();
+        write!(stdout, "{}", termion::cursor::Hide).unwrap();
+        loop {
+            let (x, y) = self.cursor;
+            write!(
+                stdout,
+                "{}{}{}\r\n{} ({}/{}){} at {},{}{} | {}{}",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1),
+                self.render(),
+                self.names[self.current],
+                self.current + 1,
+                self.frames.len(),
+                if self.modified[self.current] { "*" } else { "" },
+                x,
+                y,
+                if self.onion_skin { " | onion skin" } else { "" },
+                self.status,
+                termion::clear::UntilNewline,
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            let Some(Ok(key)) = keys.next() else {
+                break;
+            };
+            if !self.handle(key, save) {
+                break;
+            }
+        }
+        write!(stdout, "{}\r\n", termion::cursor::Show).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use crate::compression::{c_macro_name, CompactAnimation};
+use crate::frame::Groups;
+use crate::layout::Layout;
+
+/// How the frames are stored in the generated module
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Representation {
+    /// One `ImageRaw<BinaryColor>` per frame, ready to draw but not compressed
+    Raw,
+    /// A `CompressedFrame` image drawable decoding the compressed tables on the fly
+    Compressed,
+}
+
+const COMPRESSED_IMAGE: &str = r#"
+/// A frame decoded on the fly from the compressed tables when drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompressedFrame {
+    index: usize,
+}
+
+impl CompressedFrame {
+    /// The frame at `index` in the compressed tables
+    pub const fn new(index: usize) -> Self {
+        Self { index }
+    }
+    /// The `index`th frame of the group called `name`
+    pub fn from_group(name: &str, index: usize) -> Option<Self> {
+        FRAME_GROUPS
+            .iter()
+            .find(|(group, _, len)| *group == name && index < *len)
+            .map(|(_, offset, _)| Self::new(offset + index))
+    }
+    fn diff_byte(regions: &[usize], bytes_begin: usize, byte: usize) -> Option<u8> {
+        let mut offset = bytes_begin;
+        for region in regions.chunks(2) {
+            if byte < region[0] {
+                return None;
+            }
+            if byte < region[1] {
+                return Some(DIFF_BYTES[offset + byte - region[0]]);
+            }
+            offset += region[1] - region[0];
+        }
+        None
+    }
+    /// Byte at `byte` in the page-major frame
+    fn byte(&self, byte: usize) -> u8 {
+        // Every frame is stored as a diff to its parent: entry 0 for the first diff, itself
+        // a diff to an empty screen, or entry k + 1 for frame k. The closest diff wins.
+        let mut entry = self.index + 1;
+        while entry != 0 {
+            let frame = entry - 1;
+            let regions =
+                &DIFF_REGIONS[DIFF_REGIONS_BOUNDARIES[frame]..DIFF_REGIONS_BOUNDARIES[frame + 1]];
+            if let Some(value) = Self::diff_byte(regions, DIFF_BYTES_BOUNDARIES[frame], byte) {
+                return value;
+            }
+            entry = FRAME_PARENTS[frame];
+        }
+        let base_regions = &DIFF_REGIONS[..DIFF_REGIONS_BOUNDARIES[0]];
+        Self::diff_byte(base_regions, 0, byte).unwrap_or(0)
+    }
+}
+
+impl OriginDimensions for CompressedFrame {
+    fn size(&self) -> Size {
+        Size::new(WIDTH, HEIGHT)
+    }
+}
+
+impl ImageDrawable for CompressedFrame {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        self.draw_sub_image(target, &self.bounding_box())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let area = area.intersection(&self.bounding_box());
+        target.draw_iter(area.points().map(|point| {
+            let (x, y) = (point.x as usize, point.y as usize);
+            let byte = self.byte((y / 8) * WIDTH as usize + x);
+            Pixel(
+                point - area.top_left,
+                BinaryColor::from(byte & (1 << (y % 8)) != 0),
+            )
+        }))
+    }
+}
+"#;
+
+fn raw_module(frames: &[Vec<u8>], groups: &Groups, num_cols: usize, num_rows: usize) -> String {
+    let names = groups
+        .frames()
+        .iter()
+        .map(|frame| c_macro_name(&frame.to_string()))
+        .collect::<Vec<_>>();
+    let images = names
+        .iter()
+        .zip(frames.iter())
+        .map(|(name, frame)| {
+            format!(
+                "pub const {}: ImageRaw<BinaryColor> = ImageRaw::new(&{:?}, WIDTH);\n",
+                name,
+                Layout::RowMsbFirst.encode(frame, num_cols, num_rows)
+            )
+        })
+        .collect::<String>();
+    let groups = groups
+        .iter()
+        .map(|(name, offset, len)| format!("({:?}, {}, {})", name, offset, len))
+        .collect::<Vec<_>>();
+    [
+        "use embedded_graphics::image::ImageRaw;\nuse embedded_graphics::pixelcolor::BinaryColor;\n\n"
+            .to_string(),
+        format!(
+            "pub const WIDTH: u32 = {};\npub const HEIGHT: u32 = {};\n\n",
+            num_cols, num_rows
+        ),
+        images,
+        format!(
+            "\npub const FRAMES: [ImageRaw<BinaryColor>; {}] = [{}];\n",
+            names.len(),
+            names.join(", ")
+        ),
+        "// Name, index of the first frame and number of frames\n".to_string(),
+        format!(
+            "pub const FRAME_GROUPS: [(&str, usize, usize); {}] = [{}];\n",
+            groups.len(),
+            groups.join(", ")
+        ),
+    ]
+    .concat()
+}
+
+fn compressed_module(slim: &CompactAnimation, num_cols: usize, num_rows: usize) -> String {
+    [
+        "use embedded_graphics::draw_target::DrawTarget;\n\
+         use embedded_graphics::geometry::{Dimensions, OriginDimensions, Size};\n\
+         use embedded_graphics::image::ImageDrawable;\n\
+         use embedded_graphics::pixelcolor::BinaryColor;\n\
+         use embedded_graphics::primitives::{PointsIter, Rectangle};\n\
+         use embedded_graphics::Pixel;\n\n"
+            .to_string(),
+        format!(
+            "pub const WIDTH: u32 = {};\npub const HEIGHT: u32 = {};\n\n",
+            num_cols, num_rows
+        ),
+        slim.rust_declarations(""),
+        COMPRESSED_IMAGE.to_string(),
+    ]
+    .concat()
+}
+
+/// Generate a Rust module exposing the frames to the embedded-graphics ecosystem
+pub fn export(
+    representation: Representation,
+    slim: &CompactAnimation,
+    frames: &[Vec<u8>],
+    groups: &Groups,
+    num_cols: usize,
+    num_rows: usize,
+) -> String {
+    let module = match representation {
+        Representation::Raw => raw_module(frames, groups, num_cols, num_rows),
+        Representation::Compressed => compressed_module(slim, num_cols, num_rows),
+    };
+    format!(
+        "//! Generated by bongo_light. Do not edit.\n#![allow(dead_code)]\n\n{}",
+        module
+    )
+}
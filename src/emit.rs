@@ -0,0 +1,113 @@
+use crate::compression::{
+    c_macro_name, fmt_c_header_preamble, prefixed, CompactAnimation, Encoding,
+};
+use crate::sequence::Sequence;
+use std::path::Path;
+
+/// A C header declaring the compressed tables and sequences, named after `prefix`
+pub fn c_header(
+    header_name: &str,
+    prefix: &str,
+    slim: &CompactAnimation,
+    sequences: &[Sequence],
+) -> Result<String, String> {
+    // Derived from the file name, e.g. BONGO_H for bongo.h
+    let guard = c_macro_name(header_name);
+    Ok(vec![
+        fmt_c_header_preamble("", Some(&guard)),
+        slim.c_header_declarations(prefix),
+    ]
+    .into_iter()
+    .chain(
+        sequences
+            .iter()
+            .map(|sequence| {
+                Ok(format!(
+                    "\n{}",
+                    sequence.c_header_declarations(prefix, slim.num_frames())?
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+    )
+    .chain(std::iter::once(format!("\n#endif /* {} */\n", guard)))
+    .collect())
+}
+
+/// A C source defining the tables declared by `c_header`
+pub fn c_source(
+    header_name: &str,
+    prefix: &str,
+    slim: &CompactAnimation,
+    sequences: &[Sequence],
+) -> Result<String, String> {
+    Ok(vec![
+        format!(
+            "/* Generated by bongo_light. Do not edit. */\n\n#include \"{}\"\n\n",
+            header_name
+        ),
+        slim.c_source_definitions(prefix),
+    ]
+    .into_iter()
+    .chain(
+        sequences
+            .iter()
+            .map(|sequence| {
+                Ok(format!(
+                    "\n{}",
+                    sequence.c_source_definitions(prefix, slim.num_frames())?
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+    )
+    .collect())
+}
+
+/// A Rust module holding the compressed tables and sequences as constants
+pub fn rust_module(
+    prefix: &str,
+    slim: &CompactAnimation,
+    sequences: &[Sequence],
+) -> Result<String, String> {
+    let encoding = match slim.encoding() {
+        Encoding::Star => {
+            "//! Every frame is stored as a diff to the first one, itself stored as a diff to an\n\
+             //! empty screen."
+                .to_string()
+        }
+        Encoding::Tree => format!(
+            "//! Every frame is stored as a diff to its parent in a tree rooted at an empty\n\
+             //! screen: `{}` holds 0 for a frame diffed against the first diff,\n\
+             //! itself a diff to an empty screen, or k + 1 for a frame diffed against frame k.\n\
+             //! Decode a frame by applying the diffs along its chain of parents, from the top.",
+            c_macro_name(&prefixed(prefix, "FRAME_PARENTS"))
+        ),
+    };
+    Ok(vec![
+        format!(
+            "//! Compressed animation frames and playback sequences.\n//!\n{}\n\
+             //! Generated by bongo_light. Do not edit.\n\n",
+            encoding
+        ),
+        slim.rust_declarations(prefix),
+    ]
+    .into_iter()
+    .chain(
+        sequences
+            .iter()
+            .map(|sequence| {
+                Ok(format!(
+                    "\n{}",
+                    sequence.rust_declarations(prefix, slim.num_frames())?
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+    )
+    .collect())
+}
+
+/// File name of `path`, as used to include it from the generated source
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
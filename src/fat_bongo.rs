@@ -1,6 +1,7 @@
-use super::Frame;
-use super::IDLE_FRAMES;
-use super::TAP_FRAMES;
+use crate::frame::{Frame, Groups};
+
+const IDLE_FRAMES: usize = 5;
+const TAP_FRAMES: usize = 2;
 
 const ANIM_SIZE: usize = 636;
 const IDLE: [[u8; ANIM_SIZE]; IDLE_FRAMES] = [
@@ -226,13 +227,19 @@ const TAP: [[u8; ANIM_SIZE]; TAP_FRAMES] = [
     ],
 ];
 
+pub(super) fn groups() -> Groups {
+    Groups::new()
+        .with_group("idle", IDLE_FRAMES)
+        .with_group("prep", PREP.len())
+        .with_group("tap", TAP_FRAMES)
+}
+
 pub(super) fn get_frame(frame: &Frame) -> Vec<u8> {
-    match *frame {
-        Frame::Idle(index) => &IDLE[index],
-        Frame::Prep => &PREP[0],
-        Frame::Tap(index) => &TAP[index],
+    match frame.group.as_str() {
+        "idle" => &IDLE[frame.index],
+        "prep" => &PREP[frame.index],
+        "tap" => &TAP[frame.index],
+        group => panic!("Unknown frame group: {}", group),
     }
-    .iter()
-    .cloned()
-    .collect()
+    .to_vec()
 }
@@ -0,0 +1,545 @@
+use crate::oled::{self, Bitmap};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The classic 5x7 font of character LCDs, as found in QMK's and Adafruit GFX's
+/// `glcdfont.c`: one byte per column, the least significant bit at the top,
+/// for the printable ASCII characters from ' ' to '~'
+const GLCDFONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // '#'
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '''
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // ')'
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // '*'
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // '0'
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // '@'
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // 'A'
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // 'D'
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // 'G'
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // 'H'
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // 'J'
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // 'M'
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // 'N'
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // 'O'
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // 'Q'
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // 'T'
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // 'U'
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // 'V'
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // 'f'
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // 'g'
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // 'j'
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // 'l'
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // 'q'
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // 't'
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // 'u'
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // 'v'
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // 'y'
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+// Blank column between two characters of the glcdfont, as in QMK's 6x8 character cells
+const GLCDFONT_SPACING: usize = 1;
+// Blank rows between two lines of text
+const LINE_SPACING: usize = 1;
+
+struct Glyph {
+    /// Pixels of the character, as high as the font
+    bitmap: Bitmap,
+    /// Distance from the left of the character to the left of the next one
+    advance: usize,
+}
+
+/// A bitmap font, each character being as high as the font
+pub struct Font {
+    height: usize,
+    glyphs: BTreeMap<char, Glyph>,
+}
+
+impl Default for Font {
+    /// The 5x7 glcdfont
+    fn default() -> Self {
+        let glyphs = GLCDFONT
+            .iter()
+            .zip(' '..='~')
+            .map(|(columns, c)| {
+                let bitmap = Bitmap {
+                    width: columns.len(),
+                    height: 7,
+                    data: columns.to_vec(),
+                };
+                let advance = columns.len() + GLCDFONT_SPACING;
+                (c, Glyph { bitmap, advance })
+            })
+            .collect();
+        Self { height: 7, glyphs }
+    }
+}
+
+/// Turn rows of `width` pixels, each padded to whole bytes with the leftmost pixel in
+/// the most significant bit, into a bitmap of `height` rows
+fn from_rows(rows: &[u8], width: usize, height: usize) -> Bitmap {
+    let row_len = width.div_ceil(8);
+    let mut bitmap = Bitmap::new(width, height);
+    for (y, row) in rows.chunks(row_len).take(height).enumerate() {
+        for x in 0..width {
+            if row[x / 8] & (0x80 >> (x % 8)) != 0 {
+                bitmap.set_pixel(x, y, true);
+            }
+        }
+    }
+    bitmap
+}
+
+impl Font {
+    /// Load a BDF (`.bdf`) or PSF (`.psf`, version 1 or 2) font
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bdf") => Self::from_bdf(&String::from_utf8_lossy(&data)),
+            Some("psf") => Self::from_psf(&data),
+            _ => Err("unsupported font format: expected .bdf or .psf".to_string()),
+        }
+        .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+    /// Parse a font in the Glyph Bitmap Distribution Format. Every character is drawn
+    /// within the bounding box of the font, its baseline at the font's baseline.
+    pub fn from_bdf(text: &str) -> Result<Self, String> {
+        let numbers = |line: &str, count: usize| {
+            let numbers = line
+                .split_whitespace()
+                .skip(1)
+                .map(|number| number.parse::<isize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Invalid line {}: {}", line, e))?;
+            if numbers.len() < count {
+                return Err(format!("Invalid line {}: expected {} numbers", line, count));
+            }
+            Ok(numbers)
+        };
+        let mut lines = text.lines().map(str::trim);
+        let mut bounds = None;
+        let mut glyphs = BTreeMap::new();
+        while let Some(line) = lines.next() {
+            if line.starts_with("FONTBOUNDINGBOX") {
+                bounds = Some(numbers(line, 4)?);
+            } else if line.starts_with("STARTCHAR") {
+                let font = bounds
+                    .as_ref()
+                    .ok_or("Missing FONTBOUNDINGBOX before the characters")?;
+                let (mut encoding, mut advance, mut bbx) = (None, None, None);
+                let mut rows = Vec::new();
+                for line in lines.by_ref() {
+                    if line.starts_with("ENCODING") {
+                        encoding = numbers(line, 1)?.first().cloned();
+                    } else if line.starts_with("DWIDTH") {
+                        advance = numbers(line, 1)?.first().cloned();
+                    } else if line.starts_with("BBX") {
+                        bbx = Some(numbers(line, 4)?);
+                    } else if line == "ENDCHAR" {
+                        break;
+                    } else if line != "BITMAP" && bbx.is_some() {
+                        rows.push(
+                            u32::from_str_radix(line, 16)
+                                .map_err(|e| format!("Invalid bitmap row {}: {}", line, e))?,
+                        );
+                    }
+                }
+                let c = encoding
+                    .filter(|encoding| *encoding >= 0)
+                    .and_then(|encoding| std::char::from_u32(encoding as u32));
+                let (Some(c), Some(bbx)) = (c, bbx) else {
+                    // Unencoded characters can't be typed
+                    continue;
+                };
+                // Rows are read as 32 bit numbers
+                if !(0..=32).contains(&bbx[0]) {
+                    return Err(format!(
+                        "Character {:?}: {} pixels wide, up to 32 are supported",
+                        c, bbx[0]
+                    ));
+                }
+                let (width, height) = (font[0].max(0) as usize, font[1].max(0) as usize);
+                // Offsets of the character within the bounding box of the font
+                let left = bbx[2] - font[2];
+                let top = (font[1] + font[3]) - (bbx[1] + bbx[3]);
+                let mut bitmap = Bitmap::new(width, height);
+                for (y, row) in rows.iter().enumerate() {
+                    let row_bits = bbx[0].max(0) as usize;
+                    // Rows are padded to whole bytes, the leftmost pixel first
+                    let row_len = row_bits.div_ceil(8) * 8;
+                    for x in 0..row_bits {
+                        if row & (1 << (row_len - 1 - x)) != 0 {
+                            let (x, y) = (left + x as isize, top + y as isize);
+                            if x >= 0 && y >= 0 {
+                                bitmap.set_pixel(x as usize, y as usize, true);
+                            }
+                        }
+                    }
+                }
+                let advance = advance.map_or(width, |advance| advance.max(0) as usize);
+                glyphs.insert(c, Glyph { bitmap, advance });
+            }
+        }
+        let bounds = bounds.ok_or("Missing FONTBOUNDINGBOX")?;
+        Ok(Self {
+            height: bounds[1].max(0) as usize,
+            glyphs,
+        })
+    }
+    /// Parse a PC Screen Font, version 1 or 2. Characters are mapped through the
+    /// unicode table of the font if any, or else by their index.
+    pub fn from_psf(data: &[u8]) -> Result<Self, String> {
+        let truncated = || "Truncated PSF font".to_string();
+        let u32_at = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+                .ok_or_else(truncated)
+        };
+        // Header size, number of glyphs, width, height and whether there is a unicode table
+        let (header_len, len, width, height, unicode) = match data {
+            [0x36, 0x04, mode, height, ..] => (
+                4,
+                if mode & 0x01 != 0 { 512 } else { 256 },
+                8,
+                *height as usize,
+                mode & 0x06 != 0,
+            ),
+            [0x72, 0xb5, 0x4a, 0x86, ..] => (
+                u32_at(8)?,
+                u32_at(16)?,
+                u32_at(28)?,
+                u32_at(24)?,
+                u32_at(12)? & 0x01 != 0,
+            ),
+            _ => return Err("Not a PSF font".to_string()),
+        };
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid PSF glyph size {}x{}", width, height));
+        }
+        let glyph_len = width.div_ceil(8) * height;
+        let glyphs_end = len
+            .checked_mul(glyph_len)
+            .and_then(|glyphs_len| glyphs_len.checked_add(header_len))
+            .ok_or_else(truncated)?;
+        let bitmaps = data
+            .get(header_len..glyphs_end)
+            .ok_or_else(truncated)?
+            .chunks(glyph_len)
+            .map(|rows| from_rows(rows, width, height))
+            .collect::<Vec<_>>();
+        // Characters of each glyph
+        let chars: Vec<Vec<char>> = if !unicode {
+            (0..len as u32)
+                .map(|index| std::char::from_u32(index).into_iter().collect())
+                .collect()
+        } else if data[0] == 0x36 {
+            // Little endian code points, a sequence starting at 0xfffe and ending at 0xffff
+            let mut chars = vec![Vec::new()];
+            let mut in_sequence = false;
+            for unit in data[glyphs_end..].chunks_exact(2) {
+                match u16::from_le_bytes([unit[0], unit[1]]) {
+                    0xffff => {
+                        chars.push(Vec::new());
+                        in_sequence = false;
+                    }
+                    0xfffe => in_sequence = true,
+                    _ if in_sequence => (),
+                    code => chars
+                        .last_mut()
+                        .expect("There is a current glyph")
+                        .extend(std::char::from_u32(code.into())),
+                }
+            }
+            chars
+        } else {
+            // UTF-8 characters, a sequence starting at 0xfe and each glyph ending at 0xff
+            data[glyphs_end..]
+                .split(|byte| *byte == 0xff)
+                .map(|entry| {
+                    let singles = entry.split(|byte| *byte == 0xfe).next().unwrap_or(&[]);
+                    String::from_utf8_lossy(singles).chars().collect()
+                })
+                .collect()
+        };
+        let glyphs = bitmaps
+            .into_iter()
+            .zip(chars)
+            .flat_map(|(bitmap, chars)| {
+                chars.into_iter().map(move |c| {
+                    let glyph = Glyph {
+                        bitmap: bitmap.clone(),
+                        advance: width,
+                    };
+                    (c, glyph)
+                })
+            })
+            .collect();
+        Ok(Self { height, glyphs })
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// The characters drawn by the font, in order
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.keys().cloned()
+    }
+    /// Pixels of a character and distance to the next one, if the font has it
+    pub fn glyph(&self, c: char) -> Option<(&Bitmap, usize)> {
+        self.glyphs
+            .get(&c)
+            .map(|glyph| (&glyph.bitmap, glyph.advance))
+    }
+    /// Size in pixels of `text` rendered on a single line. Characters missing from
+    /// the font take as much room as a space.
+    pub fn text_size(&self, text: &str) -> (usize, usize) {
+        let blank = self.glyphs.get(&' ');
+        let widths = text.chars().map(|c| {
+            self.glyphs
+                .get(&c)
+                .or(blank)
+                .map_or((0, 0), |glyph| (glyph.advance, glyph.bitmap.width))
+        });
+        let width = widths.clone().map(|(advance, _)| advance).sum::<usize>();
+        // The last character doesn't need the room to the next one
+        let last = widths
+            .clone()
+            .next_back()
+            .map_or(0, |(advance, width)| advance.saturating_sub(width));
+        (width - last, self.height)
+    }
+    /// Size in pixels of a box holding any `len` characters of the font on a single line,
+    /// each taking as much room as the widest one
+    pub fn box_size(&self, len: usize) -> (usize, usize) {
+        let advance = self.glyphs.values().map(|glyph| glyph.advance).max();
+        let width = self.glyphs.values().map(|glyph| glyph.bitmap.width).max();
+        match (len, advance, width) {
+            (1.., Some(advance), Some(width)) => ((len - 1) * advance + width, self.height),
+            _ => (0, self.height),
+        }
+    }
+    /// Size in pixels of `text`, each of its lines being rendered below the previous one
+    pub fn text_block_size(&self, text: &str) -> (usize, usize) {
+        let lines = text.lines().collect::<Vec<_>>();
+        let width = lines
+            .iter()
+            .map(|line| self.text_size(line).0)
+            .max()
+            .unwrap_or(0);
+        let height = (lines.len() * (self.height + LINE_SPACING)).saturating_sub(LINE_SPACING);
+        (width, height)
+    }
+    /// Light the pixels of `text` on a frame of `num_cols` pixels wide, the top left corner
+    /// of the text at column `x`, row `y`. Each line is drawn below the previous one.
+    /// Characters missing from the font are left blank, and the text is clipped to the frame.
+    pub fn draw(&self, frame: &mut [u8], num_cols: usize, x: usize, y: usize, text: &str) {
+        let blank = self.glyphs.get(&' ');
+        for (line_index, line) in text.lines().enumerate() {
+            let top = y + line_index * (self.height + LINE_SPACING);
+            let mut left = x;
+            for c in line.chars() {
+                let Some(glyph) = self.glyphs.get(&c).or(blank) else {
+                    continue;
+                };
+                for glyph_y in 0..glyph.bitmap.height {
+                    for glyph_x in 0..glyph.bitmap.width {
+                        if glyph.bitmap.pixel(glyph_x, glyph_y) {
+                            oled::set_pixel(frame, num_cols, left + glyph_x, top + glyph_y, true);
+                        }
+                    }
+                }
+                left += glyph.advance;
+            }
+        }
+    }
+    /// Render `text` on a bitmap just large enough for it
+    pub fn render(&self, text: &str) -> Bitmap {
+        let (width, height) = self.text_block_size(text);
+        let mut bitmap = Bitmap::new(width, height);
+        self.draw(&mut bitmap.data, width, 0, 0, text);
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lit pixels of the glyph of `c`, and its advance
+    fn lit(font: &Font, c: char) -> (Vec<(usize, usize)>, usize) {
+        let (bitmap, advance) = font.glyph(c).expect("The character is in the font");
+        let pixels = (0..bitmap.height)
+            .flat_map(|y| (0..bitmap.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| bitmap.pixel(x, y))
+            .collect();
+        (pixels, advance)
+    }
+
+    /// A PSF2 header for `len` glyphs of `width` by `height` pixels. The glyph size
+    /// field is not read by the parser, so it may wrap around.
+    fn psf2_header(unicode: bool, len: u32, width: u32, height: u32) -> Vec<u8> {
+        let glyph_len = width.div_ceil(8).wrapping_mul(height);
+        [
+            0x864ab572,
+            0,
+            32,
+            unicode as u32,
+            len,
+            glyph_len,
+            height,
+            width,
+        ]
+        .iter()
+        .flat_map(|field: &u32| field.to_le_bytes())
+        .collect()
+    }
+
+    #[test]
+    fn parses_bdf_characters_within_the_font_box() {
+        let bdf = "STARTFONT 2.1\nFONTBOUNDINGBOX 4 4 0 -1\nCHARS 2\n\
+                   STARTCHAR A\nENCODING 65\nDWIDTH 5 0\nBBX 3 2 1 0\nBITMAP\nE0\nA0\nENDCHAR\n\
+                   STARTCHAR unencoded\nENCODING -1\nBBX 1 1 0 0\nBITMAP\n80\nENDCHAR\n\
+                   ENDFONT\n";
+        let font = Font::from_bdf(bdf).unwrap();
+        assert_eq!(font.height(), 4);
+        assert_eq!(font.chars().collect::<Vec<_>>(), vec!['A']);
+        // Baseline one row above the bottom of the font box
+        assert_eq!(
+            lit(&font, 'A'),
+            (vec![(1, 1), (2, 1), (3, 1), (1, 2), (3, 2)], 5)
+        );
+    }
+
+    #[test]
+    fn rejects_bdf_rows_over_32_pixels() {
+        let bdf = "FONTBOUNDINGBOX 33 1 0 0\n\
+                   STARTCHAR wide\nENCODING 65\nBBX 33 1 0 0\nBITMAP\n0000000080\nENDCHAR\n";
+        assert_eq!(
+            Font::from_bdf(bdf).err(),
+            Some("Character 'A': 33 pixels wide, up to 32 are supported".to_string())
+        );
+        assert!(Font::from_bdf("STARTCHAR A\nENCODING 65\nENDCHAR\n").is_err());
+    }
+
+    #[test]
+    fn parses_psf1_fonts() {
+        let mut glyphs = vec![0; 256 * 2];
+        glyphs[65 * 2..65 * 2 + 2].copy_from_slice(&[0x80, 0x01]);
+        glyphs[..2].copy_from_slice(&[0xff, 0x00]);
+        let font = Font::from_psf(&[&[0x36, 0x04, 0x00, 2][..], &glyphs].concat()).unwrap();
+        assert_eq!(font.height(), 2);
+        assert_eq!(font.chars().count(), 256);
+        assert_eq!(lit(&font, 'A'), (vec![(0, 0), (7, 1)], 8));
+
+        // Glyph 0 is 'x' and glyph 65 is 'b', a sequence of 'c' following it
+        let mut table = vec![0x78, 0x00, 0xff, 0xff];
+        table.extend(std::iter::repeat_n([0xff, 0xff], 64).flatten());
+        table.extend([0x62, 0x00, 0xfe, 0xff, 0x63, 0x00, 0xff, 0xff]);
+        table.extend(std::iter::repeat_n([0xff, 0xff], 190).flatten());
+        let font = Font::from_psf(&[&[0x36, 0x04, 0x02, 2][..], &glyphs, &table].concat()).unwrap();
+        assert_eq!(font.chars().collect::<Vec<_>>(), vec!['b', 'x']);
+        assert_eq!(lit(&font, 'b'), (vec![(0, 0), (7, 1)], 8));
+        assert_eq!(lit(&font, 'x').0.len(), 8);
+    }
+
+    #[test]
+    fn parses_psf2_fonts() {
+        // Two glyphs of 10x2 pixels, rows padded to 2 bytes
+        let glyphs = [0, 0, 0, 0, 0x00, 0x40, 0x80, 0x00];
+        let font =
+            Font::from_psf(&[psf2_header(false, 2, 10, 2), glyphs.to_vec()].concat()).unwrap();
+        assert_eq!(font.chars().collect::<Vec<_>>(), vec!['\0', '\u{1}']);
+        assert_eq!(lit(&font, '\u{1}'), (vec![(9, 0), (0, 1)], 10));
+
+        // Glyph 0 is 'é' and glyph 1 is 'a', a sequence of 'b' following it
+        let table = ["é".as_bytes(), &[0xff, b'a', 0xfe, b'b', 0xff]].concat();
+        let data = [psf2_header(true, 2, 10, 2), glyphs.to_vec(), table].concat();
+        let font = Font::from_psf(&data).unwrap();
+        assert_eq!(font.chars().collect::<Vec<_>>(), vec!['a', 'é']);
+        assert_eq!(lit(&font, 'a'), (vec![(9, 0), (0, 1)], 10));
+    }
+
+    #[test]
+    fn rejects_empty_or_truncated_psf_glyphs() {
+        assert_eq!(
+            Font::from_psf(&[0x36, 0x04, 0x00, 0]).err(),
+            Some("Invalid PSF glyph size 8x0".to_string())
+        );
+        assert_eq!(
+            Font::from_psf(&psf2_header(false, 2, 0, 8)).err(),
+            Some("Invalid PSF glyph size 0x8".to_string())
+        );
+        assert_eq!(
+            Font::from_psf(&psf2_header(false, u32::MAX, 32, u32::MAX)).err(),
+            Some("Truncated PSF font".to_string())
+        );
+        assert!(Font::from_psf(&[0x36, 0x04, 0x00, 2, 0xff]).is_err());
+        assert!(Font::from_psf(b"not a font").is_err());
+    }
+}
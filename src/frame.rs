@@ -0,0 +1,86 @@
+/// A frame, identified by the name of its group and its index within the group
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub group: String,
+    pub index: usize,
+}
+
+impl Frame {
+    pub fn new(group: &str, index: usize) -> Self {
+        Self {
+            group: group.to_string(),
+            index,
+        }
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.group, self.index)
+    }
+}
+
+impl std::str::FromStr for Frame {
+    type Err = String;
+
+    /// Parse the `{group}_{index}` form used by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (group, index) = s
+            .rsplit_once('_')
+            .ok_or_else(|| format!("Invalid frame {}: expected <group>_<index>", s))?;
+        let index = index
+            .parse()
+            .map_err(|e| format!("Invalid frame index in {}: {}", s, e))?;
+        Ok(Self::new(group, index))
+    }
+}
+
+struct Group {
+    name: String,
+    len: usize,
+}
+
+/// Named groups of frames (e.g. idle, prep and tap for the bongo cat),
+/// stored one after the other in the compressed tables
+#[derive(Default)]
+pub struct Groups {
+    groups: Vec<Group>,
+}
+
+impl Groups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_group(mut self, name: &str, len: usize) -> Self {
+        self.groups.push(Group {
+            name: name.to_string(),
+            len,
+        });
+        self
+    }
+    /// Name, offset in the compressed tables and number of frames of each group
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize, usize)> {
+        let mut offset = 0;
+        self.groups.iter().map(move |group| {
+            offset += group.len;
+            (group.name.as_str(), offset - group.len, group.len)
+        })
+    }
+    pub fn len(&self, name: &str) -> usize {
+        self.iter()
+            .find(|(group, _, _)| *group == name)
+            .map_or(0, |(_, _, len)| len)
+    }
+    /// Index of the frame in the compressed tables
+    pub fn frame_index(&self, frame: &Frame) -> Option<usize> {
+        self.iter()
+            .find(|(group, _, len)| *group == frame.group && frame.index < *len)
+            .map(|(_, offset, _)| offset + frame.index)
+    }
+    /// All the frames, in the order of the compressed tables
+    pub fn frames(&self) -> Vec<Frame> {
+        self.iter()
+            .flat_map(|(group, _, len)| (0..len).map(move |index| Frame::new(group, index)))
+            .collect()
+    }
+}
@@ -0,0 +1,133 @@
+use crate::compression::{c_macro_name, fmt_as_rust_array, prefixed};
+use crate::font::Font;
+use crate::oled;
+
+// Characters per row of the preview
+const PREVIEW_CHARS_PER_ROW: usize = 16;
+
+/// A font laid out as QMK's `glcdfont.c`: every character takes `width` bytes, one
+/// per column with the least significant bit at the top, from `start` to `end`
+pub struct Table {
+    pub width: usize,
+    pub start: u8,
+    pub end: u8,
+    pub data: Vec<u8>,
+}
+
+impl Table {
+    /// Lay out the characters of `font` up to 255, the ones QMK can index with a byte,
+    /// each in a cell as wide as the widest character
+    pub fn from_font(font: &Font) -> Result<Self, String> {
+        if font.height() > 8 {
+            return Err(format!(
+                "The font is {} pixels high: glcdfont characters are up to 8 pixels high",
+                font.height()
+            ));
+        }
+        let chars = font
+            .chars()
+            .filter(|c| u32::from(*c) <= 255)
+            .collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (chars.first(), chars.last()) else {
+            return Err("The font has no character from 0 to 255".to_string());
+        };
+        let width = chars
+            .iter()
+            .filter_map(|c| font.glyph(*c))
+            .map(|(bitmap, advance)| advance.max(bitmap.width))
+            .max()
+            .unwrap_or(0);
+        let (start, end) = (u32::from(*first) as u8, u32::from(*last) as u8);
+        let mut data = vec![0; (usize::from(end - start) + 1) * width];
+        for (index, code) in (start..=end).enumerate() {
+            let Some((bitmap, _)) = font.glyph(char::from(code)) else {
+                continue;
+            };
+            let cell = &mut data[index * width..(index + 1) * width];
+            for y in 0..bitmap.height {
+                for x in 0..bitmap.width.min(width) {
+                    if bitmap.pixel(x, y) {
+                        oled::set_pixel(cell, width, x, y, true);
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            start,
+            end,
+            data,
+        })
+    }
+    /// A `glcdfont.c` for QMK's OLED driver, one character per line
+    pub fn c_source(&self) -> String {
+        let glyphs = self
+            .data
+            .chunks(self.width)
+            .zip(self.start..=self.end)
+            .map(|(columns, code)| {
+                let bytes = columns
+                    .iter()
+                    .map(|byte| format!("0x{:02x},", byte))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let c = char::from(code);
+                if c.is_ascii_graphic() || c == ' ' {
+                    format!("    {} // {:?}\n", bytes, c)
+                } else {
+                    format!("    {} // 0x{:02x}\n", bytes, code)
+                }
+            })
+            .collect::<String>();
+        format!(
+            "/* Generated by bongo_light. Do not edit.
+ *
+ * Use it with QMK's OLED driver from config.h:
+ *
+ *     #define OLED_FONT_H \"glcdfont.c\"
+ *     #define OLED_FONT_WIDTH {}
+ *     #define OLED_FONT_HEIGHT 8
+ *     #define OLED_FONT_START {}
+ *     #define OLED_FONT_END {}
+ */
+
+#include \"progmem.h\"
+
+static const unsigned char PROGMEM font[] = {{
+{}}};
+",
+            self.width, self.start, self.end, glyphs
+        )
+    }
+    /// The table and its layout as Rust constants, named after `prefix`
+    pub fn rust_declarations(&self, prefix: &str) -> String {
+        [
+            fmt_as_rust_array(prefix, "font", "u8", &self.data),
+            format!(
+                "pub const {}: usize = {};",
+                c_macro_name(&prefixed(prefix, "font_width")),
+                self.width
+            ),
+            format!(
+                "pub const {}: u8 = {};",
+                c_macro_name(&prefixed(prefix, "font_start")),
+                self.start
+            ),
+            format!(
+                "pub const {}: u8 = {};",
+                c_macro_name(&prefixed(prefix, "font_end")),
+                self.end
+            ),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+    }
+    /// Every character of the table, drawn on rows of 16
+    pub fn preview(&self) -> String {
+        let num_cols = PREVIEW_CHARS_PER_ROW * self.width;
+        let mut frame = self.data.clone();
+        frame.resize(self.data.len().div_ceil(num_cols) * num_cols, 0);
+        oled::render(&frame, num_cols)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::compression::Diff;
+use crate::oled;
+use crate::pbm;
+use itertools::Itertools;
+use termion::color;
+
+/// How many frames differ from the base frame of the compressed tables
+/// (the first one), per pixel and per byte
+pub struct Heatmap {
+    num_cols: usize,
+    num_rows: usize,
+    /// Row-major count for each pixel
+    pixels: Vec<usize>,
+    /// Count for each page-major byte, as stored by the diffs
+    bytes: Vec<usize>,
+    num_frames: usize,
+}
+
+// From cold to hot
+const SHADES: [&str; 5] = ["  ", "░░", "▒▒", "▓▓", "██"];
+
+/// Render a count from 0 to `max` for each pixel in the terminal,
+/// from blue and empty for 0 to red and filled for `max`
+pub fn render(
+    num_cols: usize,
+    num_rows: usize,
+    max: usize,
+    count: &dyn Fn(usize, usize) -> usize,
+) -> String {
+    let max = max.max(1);
+    (0..num_rows)
+        .map(|y| {
+            (0..num_cols)
+                .map(|x| {
+                    let count = count(x, y);
+                    let heat = (count * (SHADES.len() - 1)).div_ceil(max);
+                    // From blue to red on the 6x6x6 color cube
+                    let red = (count * 5).div_ceil(max) as u8;
+                    format!(
+                        "{}{}",
+                        color::Fg(color::AnsiValue::rgb(red, 0, 5 - red)),
+                        SHADES[heat]
+                    )
+                })
+                .collect::<String>()
+                + &color::Fg(color::Reset).to_string()
+        })
+        .join("\n")
+}
+
+impl Heatmap {
+    pub fn new(frames: &[Vec<u8>], num_cols: usize, num_rows: usize) -> Self {
+        let base = &frames[0];
+        let mut pixels = vec![0; num_cols * num_rows];
+        let mut bytes = vec![0; base.len()];
+        for frame in frames.iter() {
+            for (begin, end) in Diff::from_original_and_altered(base, frame).regions() {
+                for count in bytes[begin..end].iter_mut() {
+                    *count += 1;
+                }
+            }
+            for (index, count) in pixels.iter_mut().enumerate() {
+                let (x, y) = (index % num_cols, index / num_cols);
+                if oled::pixel(base, num_cols, x, y) != oled::pixel(frame, num_cols, x, y) {
+                    *count += 1;
+                }
+            }
+        }
+        Self {
+            num_cols,
+            num_rows,
+            pixels,
+            bytes,
+            num_frames: frames.len(),
+        }
+    }
+    /// Count of the byte covering the pixel at column `x`, row `y`
+    fn byte_count(&self, x: usize, y: usize) -> usize {
+        self.bytes
+            .get((y / 8) * self.num_cols + x)
+            .cloned()
+            .unwrap_or(0)
+    }
+    fn render(&self, count: &dyn Fn(usize, usize) -> usize) -> String {
+        render(self.num_cols, self.num_rows, self.num_frames, count)
+    }
+    /// Render the count of each pixel in the terminal, hotter pixels changing more often
+    pub fn render_pixels(&self) -> String {
+        self.render(&|x, y| self.pixels[y * self.num_cols + x])
+    }
+    /// Render the count of each byte in the terminal, over the 8 pixels it covers
+    pub fn render_bytes(&self) -> String {
+        self.render(&|x, y| self.byte_count(x, y))
+    }
+    /// The count of each pixel as a PGM image, brighter pixels changing more often
+    pub fn pixels_image(&self) -> String {
+        pbm::graymap(&self.pixels, self.num_cols, self.num_frames)
+    }
+    /// The count of each byte as a PGM image, over the 8 pixels it covers
+    pub fn bytes_image(&self) -> String {
+        let counts = (0..self.num_rows)
+            .flat_map(|y| (0..self.num_cols).map(move |x| self.byte_count(x, y)))
+            .collect::<Vec<_>>();
+        pbm::graymap(&counts, self.num_cols, self.num_frames)
+    }
+    /// Number of bytes which differ from the base in at least one frame
+    pub fn changing_bytes(&self) -> usize {
+        self.bytes.iter().filter(|count| **count > 0).count()
+    }
+    pub fn num_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::ascii::{self, Block};
+use crate::pbm;
+use std::path::Path;
+
+/// Read a frame of `frame_len` bytes from a PBM file (`.pbm`), raw page-major bytes (`.bin`)
+/// or a text drawing (`.txt`)
+pub fn read_frame(path: &Path, num_cols: usize, frame_len: usize) -> Result<Vec<u8>, String> {
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("pbm") => pbm::to_frame(&data, num_cols, frame_len)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Some("txt") => ascii::to_frame(&String::from_utf8_lossy(&data), num_cols, frame_len)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Some("bin") if data.len() == frame_len => Ok(data),
+        Some("bin") => Err(format!(
+            "{}: expected {} bytes, found {}",
+            path.display(),
+            frame_len,
+            data.len()
+        )),
+        _ => Err(format!("{}: unsupported image format", path.display())),
+    }
+}
+
+/// Write a frame of `num_cols` by `num_rows` pixels to an image, in the format given by
+/// its extension. A text drawing keeps the name and duration of the one it replaces.
+pub fn write_frame(
+    path: &Path,
+    frame: &[u8],
+    num_cols: usize,
+    num_rows: usize,
+) -> Result<(), String> {
+    let data = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("pbm") => pbm::from_frame(frame, num_cols, num_rows).into_bytes(),
+        Some("bin") => frame.to_vec(),
+        Some("txt") => {
+            let previous = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|text| ascii::parse(&text).ok())
+                .and_then(|blocks| blocks.into_iter().next());
+            let (name, duration) = match previous {
+                Some(block) => (block.name, block.duration),
+                None => (
+                    path.file_stem()
+                        .map_or(String::new(), |stem| stem.to_string_lossy().to_string()),
+                    None,
+                ),
+            };
+            Block {
+                name,
+                duration,
+                num_cols,
+                num_rows,
+                frame: frame.to_vec(),
+            }
+            .to_string()
+            .into_bytes()
+        }
+        _ => return Err(format!("{}: unsupported image format", path.display())),
+    };
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_drawings_keep_their_header() {
+        let path = std::env::temp_dir().join(format!("bongo_light_{}.txt", std::process::id()));
+        std::fs::write(&path, "name: idle_3\nduration_ms: 250\n.#.\n#.#\n").unwrap();
+        write_frame(&path, &[0x02, 0x00, 0x02], 3, 2).unwrap();
+        let text = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text.unwrap(), "name: idle_3\nduration_ms: 250\n...\n#.#\n");
+    }
+}
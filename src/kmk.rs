@@ -0,0 +1,94 @@
+use crate::bmp;
+use crate::compression::{with_prefix, CompactAnimation};
+
+/// The files making up a CircuitPython animation for KMK keyboards
+pub struct Module {
+    pub python: String,
+    pub sprite_sheet: Vec<u8>,
+}
+
+const HEADER: &str = r#"# Generated by bongo_light. Do not edit.
+#
+# Either load bongo.bmp as a sprite sheet:
+#
+#     bitmap, palette = adafruit_imageload.load("bongo.bmp")
+#     sprite = displayio.TileGrid(bitmap, pixel_shader=palette, tile_width=BONGO_WIDTH, tile_height=BONGO_HEIGHT)
+#     sprite[0] = bongo_frame_index("idle", 0)
+#
+# or decode the compressed frames from this module:
+#
+#     bitmap = displayio.Bitmap(BONGO_WIDTH, BONGO_HEIGHT, 2)
+#     bongo_to_bitmap(bongo_reconstruct_frame(bongo_frame_index("idle", 0)), bitmap)
+
+from array import array
+
+"#;
+
+const DECODER: &str = r#"
+
+def _bongo_apply_diff(frame, regions_begin, regions_end, bytes_begin):
+    byte_index = bytes_begin
+    for region in range(regions_begin, regions_end, 2):
+        for index in range(BONGO_DIFF_REGIONS[region], BONGO_DIFF_REGIONS[region + 1]):
+            frame[index] = BONGO_DIFF_BYTES[byte_index]
+            byte_index += 1
+
+
+def bongo_reconstruct_frame(index):
+    """Decode a frame into page-major bytes, each representing 8 rows of a column"""
+    # Every frame is stored as a diff to its parent: entry 0 for the first diff, itself
+    # a diff to an empty screen, or entry k + 1 for frame k. Apply the chain from the top.
+    chain = []
+    entry = index + 1
+    while entry != 0:
+        chain.append(entry - 1)
+        entry = BONGO_FRAME_PARENTS[entry - 1]
+    frame = bytearray(BONGO_FRAME_SIZE)
+    _bongo_apply_diff(frame, 0, BONGO_DIFF_REGIONS_BOUNDARIES[0], 0)
+    for parent in reversed(chain):
+        _bongo_apply_diff(
+            frame,
+            BONGO_DIFF_REGIONS_BOUNDARIES[parent],
+            BONGO_DIFF_REGIONS_BOUNDARIES[parent + 1],
+            BONGO_DIFF_BYTES_BOUNDARIES[parent],
+        )
+    return frame
+
+
+def bongo_frame_index(group, index):
+    offset, count = BONGO_FRAME_GROUPS[group]
+    return offset + index % count
+
+
+def bongo_to_bitmap(frame, bitmap):
+    """Copy a decoded frame into a 2 color displayio.Bitmap"""
+    for y in range(min(BONGO_HEIGHT, bitmap.height)):
+        for x in range(min(BONGO_WIDTH, bitmap.width)):
+            index = (y // 8) * BONGO_WIDTH + x
+            bitmap[x, y] = (frame[index] >> (y % 8)) & 1 if index < BONGO_FRAME_SIZE else 0
+"#;
+
+/// Generate a Python module holding the compressed frames and their decoder,
+/// along with a BMP sprite sheet of the decoded frames, all named after `prefix`
+pub fn export(
+    prefix: &str,
+    slim: &CompactAnimation,
+    frames: &[Vec<u8>],
+    num_cols: usize,
+    num_rows: usize,
+) -> Module {
+    let python = [
+        with_prefix(HEADER, prefix),
+        with_prefix(
+            &format!("BONGO_WIDTH = {}\nBONGO_HEIGHT = {}\n", num_cols, num_rows),
+            prefix,
+        ),
+        slim.python_declarations(prefix),
+        with_prefix(DECODER, prefix),
+    ]
+    .concat();
+    Module {
+        python,
+        sprite_sheet: bmp::sprite_sheet(frames, num_cols, num_rows),
+    }
+}
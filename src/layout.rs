@@ -0,0 +1,89 @@
+use crate::oled;
+
+/// How the pixels of a monochrome bitmap are packed into bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    /// Each byte represents 8 rows of a column with the top pixel in the least
+    /// significant bit, as sent to SSD1306 displays and drawn by u8x8 tiles
+    PageVertical,
+    /// Each byte represents 8 columns of a row with the leftmost pixel in the
+    /// most significant bit, as in PBM, BMP, LVGL and Adafruit GFX bitmaps
+    RowMsbFirst,
+    /// Each byte represents 8 columns of a row with the leftmost pixel in the
+    /// least significant bit, as in XBM and u8g2 bitmaps
+    RowLsbFirst,
+}
+
+impl Layout {
+    /// Convert a page-major frame (each byte represents 8 rows of a column) into
+    /// `num_cols` by `num_rows` pixels in this layout.
+    /// Rows (or pages) are padded to a whole number of bytes with unlit pixels.
+    pub fn encode(&self, frame: &[u8], num_cols: usize, num_rows: usize) -> Vec<u8> {
+        let pack = |pixels: &mut dyn Iterator<Item = bool>, msb_first: bool| {
+            pixels
+                .enumerate()
+                .filter(|(_, on)| *on)
+                .fold(0, |acc, (bit, _)| {
+                    acc | if msb_first { 0x80 >> bit } else { 1 << bit }
+                })
+        };
+        match self {
+            Layout::PageVertical => (0..num_rows.div_ceil(8))
+                .flat_map(|page| {
+                    (0..num_cols).map(move |x| {
+                        pack(
+                            &mut (0..8).map(|bit| {
+                                page * 8 + bit < num_rows
+                                    && oled::pixel(frame, num_cols, x, page * 8 + bit)
+                            }),
+                            false,
+                        )
+                    })
+                })
+                .collect(),
+            Layout::RowMsbFirst | Layout::RowLsbFirst => {
+                let msb_first = *self == Layout::RowMsbFirst;
+                (0..num_rows)
+                    .flat_map(|y| {
+                        (0..num_cols.div_ceil(8)).map(move |byte| {
+                            pack(
+                                &mut (0..8)
+                                    .map(|bit| oled::pixel(frame, num_cols, byte * 8 + bit, y)),
+                                msb_first,
+                            )
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 by 3 pixels, lit at (0, 0), (9, 0), (1, 1) and (8, 2), plus (2, 5) below the
+    // last row, which no layout may keep
+    const FRAME: [u8; 10] = [0x01, 0x02, 0x20, 0, 0, 0, 0, 0, 0x04, 0x01];
+
+    #[test]
+    fn packs_pages_top_pixel_first() {
+        assert_eq!(
+            Layout::PageVertical.encode(&FRAME, 10, 3),
+            vec![0x01, 0x02, 0, 0, 0, 0, 0, 0, 0x04, 0x01]
+        );
+    }
+
+    #[test]
+    fn packs_padded_rows_leftmost_pixel_first() {
+        assert_eq!(
+            Layout::RowMsbFirst.encode(&FRAME, 10, 3),
+            vec![0x80, 0x40, 0x40, 0x00, 0x00, 0x80]
+        );
+        assert_eq!(
+            Layout::RowLsbFirst.encode(&FRAME, 10, 3),
+            vec![0x01, 0x02, 0x02, 0x00, 0x00, 0x01]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::animation;
+use crate::frame::{Frame, Groups};
+use crate::oled;
+use std::io::Write;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+
+const TICK: std::time::Duration = std::time::Duration::from_millis(20);
+const THRESHOLD_STEP: f64 = 5.;
+
+/// Let the keystrokes typed in the terminal drive the animation.
+/// Arrow keys tune the thresholds: up/down for tapping, left/right for prepping.
+/// Escape or Ctrl-C exits.
+pub fn run(get_frame: &dyn Fn(&Frame) -> Vec<u8>, groups: &Groups, num_cols: usize) {
+    let mut stdout = std::io::stdout()
+        .into_raw_mode()
+        .expect("Failed to switch the terminal to raw mode");
+    let mut keys = termion::async_stdin().keys();
+    let mut animator = animation::Animator::new(animation::Config::default(), groups);
+    let start = std::time::Instant::now();
+    write!(stdout, "{}", termion::cursor::Hide).unwrap();
+    'running: loop {
+        let now = start.elapsed();
+        for key in keys.by_ref().filter_map(Result::ok) {
+            let config = animator.config_mut();
+            match key {
+                Key::Esc | Key::Ctrl('c') => break 'running,
+                Key::Up => config.tap_wpm += THRESHOLD_STEP,
+                Key::Down => config.tap_wpm = (config.tap_wpm - THRESHOLD_STEP).max(0.),
+                Key::Right => config.prep_wpm += THRESHOLD_STEP,
+                Key::Left => config.prep_wpm = (config.prep_wpm - THRESHOLD_STEP).max(0.),
+                _ => animator.key_pressed(now),
+            }
+        }
+        let frame = animator.frame(now);
+        // In raw mode, a line feed doesn't bring the cursor back to the first column
+        let screen = oled::render(&get_frame(&frame), num_cols).replace("\n", "\r\n");
+        let config = animator.config();
+        write!(
+            stdout,
+            "{}{}{}\r\n{:>5.1} wpm | prep from {} wpm, tap from {} wpm | {}{}",
+            termion::clear::All,
+            termion::cursor::Goto(1, 1),
+            screen,
+            animator.wpm(now),
+            config.prep_wpm,
+            config.tap_wpm,
+            frame,
+            termion::clear::UntilNewline,
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+        std::thread::sleep(TICK);
+    }
+    write!(stdout, "{}\r\n", termion::cursor::Show).unwrap();
+}
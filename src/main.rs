@@ -1,54 +1,1003 @@
-use termion;
+mod adafruit_gfx;
+mod animation;
+mod ascii;
+mod bmp;
+mod burn_in;
+mod compose;
 mod compression;
+mod editor;
+mod embedded_graphics;
+mod emit;
 mod fat_bongo;
+mod font;
+mod frame;
+mod glcdfont;
+mod heatmap;
+mod image;
+mod kmk;
+mod layout;
+mod live;
+mod manifest;
 mod oled;
+mod pbm;
+mod qmk;
+mod sequence;
 mod slim_bongo;
+mod stats;
+mod transform;
+mod u8g2;
+mod zmk;
+
+use frame::{Frame, Groups};
 
-const IDLE_FRAMES: usize = 5;
-const TAP_FRAMES: usize = 2;
 const NUM_COLS: usize = 128;
-const SLEEP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
-enum Frame {
-    Idle(usize),
-    Prep,
-    Tap(usize),
-}
-
-fn all_frames() -> Vec<Frame> {
-    let mut all_frames = (0..IDLE_FRAMES)
-        .map(|index| Frame::Idle(index))
-        .collect::<Vec<Frame>>();
-    all_frames.extend(std::iter::once(Frame::Prep));
-    all_frames.extend((0..TAP_FRAMES).map(|index| Frame::Tap(index)));
-    all_frames
-}
-
-fn display_bongo(get_frame: &dyn Fn(&Frame) -> Vec<u8>) {
-    for frame in all_frames().iter().map(|frame| get_frame(frame)) {
-        print!(
-            "{}{}{}",
-            termion::clear::All,
-            termion::cursor::Goto(1, 1),
-            oled::render(&frame, NUM_COLS)
+const NUM_ROWS: usize = 32;
+const IDLE_FRAME_DURATION: std::time::Duration = std::time::Duration::from_millis(200);
+const TAP_FRAME_DURATION: std::time::Duration = std::time::Duration::from_millis(150);
+const SEQUENCE_DEMO_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+const BURN_IN_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+/// Index of the frame in the compressed tables
+fn frame_index(groups: &Groups, frame: &Frame) -> usize {
+    groups
+        .frame_index(frame)
+        .unwrap_or_else(|| panic!("Unknown frame: {}", frame))
+}
+
+fn bongo_sequences(groups: &Groups) -> Vec<sequence::Sequence> {
+    let steps = |group: &str, duration| {
+        (0..groups.len(group))
+            .map(|index| (frame_index(groups, &Frame::new(group, index)), duration))
+            .collect::<Vec<_>>()
+    };
+    let idle = steps("idle", IDLE_FRAME_DURATION);
+    let tap = steps("tap", TAP_FRAME_DURATION);
+    vec![
+        sequence::Sequence::new("idle").with_section(sequence::Playback::PingPong, &idle),
+        sequence::Sequence::new("typing")
+            .with_section(
+                sequence::Playback::Once,
+                &steps("prep", IDLE_FRAME_DURATION),
+            )
+            .with_section(sequence::Playback::Repeat(4), &tap)
+            .with_section(sequence::Playback::Loop, &idle),
+    ]
+}
+
+fn display_bongo(
+    get_frame: &dyn Fn(usize) -> Vec<u8>,
+    sequences: &[sequence::Sequence],
+    num_cols: usize,
+) {
+    for sequence in sequences.iter() {
+        let mut elapsed = std::time::Duration::from_secs(0);
+        for step in sequence.play_for(SEQUENCE_DEMO_DURATION) {
+            let frame = match sequence.pixel_shift() {
+                Some(pixel_shift) => {
+                    let (dx, dy) = pixel_shift.offset(elapsed);
+                    oled::shift(&get_frame(step.frame), num_cols, dx as isize, dy as isize)
+                }
+                None => get_frame(step.frame),
+            };
+            print!(
+                "{}{}{}",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1),
+                oled::render(&frame, num_cols)
+            );
+            std::thread::sleep(step.duration);
+            elapsed += step.duration;
+        }
+    }
+}
+
+fn replay_keystroke_log(path: &str) {
+    let log = std::fs::read_to_string(path).expect("Failed to read keystroke log");
+    let keypresses = animation::parse_keystroke_log(&log).expect("Invalid keystroke log");
+    let timeline = animation::replay(
+        animation::Config::default(),
+        &slim_bongo::groups(),
+        &keypresses,
+        std::time::Duration::from_millis(10),
+    );
+    for (time, frame, wpm) in timeline {
+        println!(
+            "{:>8}ms {:>9} {:>5.1} wpm",
+            time.as_millis(),
+            frame.to_string(),
+            wpm
         );
-        std::thread::sleep(SLEEP_INTERVAL);
     }
 }
 
-fn main() {
-    display_bongo(&fat_bongo::get_frame);
-    let fat_frames = all_frames()
+fn demo() {
+    let groups = fat_bongo::groups();
+    let all_frames = groups.frames();
+    let sequences = bongo_sequences(&groups);
+    display_bongo(
+        &|index| fat_bongo::get_frame(&all_frames[index]),
+        &sequences,
+        NUM_COLS,
+    );
+    let fat_frames = all_frames
         .iter()
-        .map(|frame| fat_bongo::get_frame(frame))
+        .map(fat_bongo::get_frame)
         .collect::<Vec<_>>();
 
-    let slim = compression::compress_frames(&fat_frames);
+    let slim = compression::compress_frames(&[("", &groups, &fat_frames)]);
     print!(
-        "{}{}{}",
+        "{}{}{}{}",
         termion::clear::All,
         termion::cursor::Goto(1, 1),
+        emitted(emit::rust_module("", &slim, &sequences)),
         slim
     );
+    for sequence in sequences.iter() {
+        print!("{}", sequence);
+    }
     std::thread::sleep(std::time::Duration::from_secs(1));
-    display_bongo(&slim_bongo::get_frame);
+    display_bongo(
+        &|index| slim_bongo::get_frame(&all_frames[index]),
+        &sequences,
+        NUM_COLS,
+    );
+}
+
+fn load_manifest(path: &str) -> manifest::Manifest {
+    manifest::Manifest::load(std::path::Path::new(path)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+fn build_animation(manifest: &manifest::Manifest) -> manifest::Animation {
+    manifest.build().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Generated code, or exit with the reason it can't be generated
+fn emitted(text: Result<String, String>) -> String {
+    text.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+fn build(path: &str) {
+    let manifest = load_manifest(path);
+    let animation = build_animation(&manifest);
+    let slim = compress(&animation);
+    let header = manifest.output_path(&manifest.output.header);
+    let source = manifest.output_path(&manifest.output.source);
+    let rust = manifest.output_path(&manifest.output.rust);
+    if header.is_none() && source.is_none() && rust.is_none() && manifest.output.overlays.is_none()
+    {
+        eprintln!(
+            "Nothing to build: set header, source, rust or overlays in the [output] section of {}",
+            path
+        );
+        std::process::exit(1);
+    }
+    if let Some(header) = &header {
+        let text = emitted(emit::c_header(
+            &emit::file_name(header),
+            &manifest.output.prefix,
+            &slim,
+            &animation.sequences,
+        ));
+        std::fs::write(header, text).expect("Failed to write header");
+    }
+    if let Some(source) = &source {
+        let Some(header) = &header else {
+            eprintln!(
+                "The C source includes the header: set header in the [output] section of {}",
+                path
+            );
+            std::process::exit(1);
+        };
+        let text = emitted(emit::c_source(
+            &emit::file_name(header),
+            &manifest.output.prefix,
+            &slim,
+            &animation.sequences,
+        ));
+        std::fs::write(source, text).expect("Failed to write source");
+    }
+    if let Some(rust) = &rust {
+        let mut text = emitted(emit::rust_module(
+            &manifest.output.prefix,
+            &slim,
+            &animation.sequences,
+        ));
+        if !animation.overlays.is_empty() {
+            text += &format!(
+                "\n{}",
+                compose::rust_declarations(
+                    &manifest.output.prefix,
+                    &animation.overlays,
+                    &animation.font
+                )
+            );
+        }
+        std::fs::write(rust, text).expect("Failed to write Rust module");
+    }
+    if let Some(overlays) = manifest.output_path(&manifest.output.overlays) {
+        let text = compose::c_header(
+            &manifest.output.prefix,
+            &animation.overlays,
+            &animation.font,
+        );
+        std::fs::write(overlays, text).expect("Failed to write overlays header");
+    }
+    eprint!("{}", slim);
+    for sequence in animation.sequences.iter() {
+        eprint!("{}", sequence);
+    }
+}
+
+/// Compress several manifests into shared tables written to `dir`,
+/// each animation being named after its manifest.
+/// The shared tables are star encoded and their names are not prefixed, so manifests
+/// setting a `tree` encoding or an output `prefix` are rejected.
+fn bundle(dir: &str, paths: &[&str]) {
+    let animations = paths
+        .iter()
+        .map(|path| {
+            let name = std::path::Path::new(path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let manifest = load_manifest(path);
+            if manifest.encoding != compression::Encoding::Star {
+                eprintln!(
+                    "{}: bundles are star encoded, remove the encoding of the manifest",
+                    path
+                );
+                std::process::exit(1);
+            }
+            if !manifest.output.prefix.is_empty() {
+                eprintln!(
+                    "{}: bundles are not prefixed, remove the output prefix of the manifest",
+                    path
+                );
+                std::process::exit(1);
+            }
+            (name, build_animation(&manifest))
+        })
+        .collect::<Vec<_>>();
+    let mut names = std::collections::BTreeSet::new();
+    if let Some((name, _)) = animations
+        .iter()
+        .find(|(name, _)| !names.insert(compression::c_identifier(name)))
+    {
+        eprintln!(
+            "Two animations of the bundle are named {}: rename one of the manifests",
+            name
+        );
+        std::process::exit(1);
+    }
+    let (_, first) = &animations[0];
+    if animations.iter().any(|(_, animation)| {
+        (animation.num_cols, animation.num_rows) != (first.num_cols, first.num_rows)
+    }) {
+        eprintln!("All the animations of a bundle must target the same display size");
+        std::process::exit(1);
+    }
+    let slim = compression::compress_frames(
+        &animations
+            .iter()
+            .map(|(name, animation)| {
+                (
+                    name.as_str(),
+                    &animation.groups,
+                    animation.frames.as_slice(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let sequences = animations
+        .iter()
+        .flat_map(|(name, animation)| {
+            let indices = slim
+                .frame_indices(name)
+                .expect("Every animation of a bundle has an index table");
+            animation
+                .sequences
+                .iter()
+                .map(move |sequence| sequence.bundled(name, indices))
+        })
+        .collect::<Vec<_>>();
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    std::fs::write(
+        dir.join("animations.h"),
+        emitted(emit::c_header("animations.h", "", &slim, &sequences)),
+    )
+    .expect("Failed to write animations.h");
+    std::fs::write(
+        dir.join("animations.c"),
+        emitted(emit::c_source("animations.h", "", &slim, &sequences)),
+    )
+    .expect("Failed to write animations.c");
+    std::fs::write(
+        dir.join("animations.rs"),
+        emitted(emit::rust_module("", &slim, &sequences)),
+    )
+    .expect("Failed to write animations.rs");
+    eprint!("{}", slim);
+    for sequence in sequences.iter() {
+        eprint!("{}", sequence);
+    }
+}
+
+fn print_stats(mcu: &str, manifest: Option<&str>) {
+    let profiles = match mcu {
+        "all" => stats::MCU_PROFILES.iter().collect(),
+        name => match stats::profile(name) {
+            Some(profile) => vec![profile],
+            None => {
+                eprintln!(
+                    "Unknown MCU {}: expected all or one of {}",
+                    name,
+                    stats::MCU_PROFILES
+                        .iter()
+                        .map(|profile| profile.name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                std::process::exit(1);
+            }
+        },
+    };
+    let animation = load_animation(manifest);
+    let slim = compress(&animation);
+    print!(
+        "{}",
+        stats::report(&slim, &animation.groups, &animation.sequences, &profiles)
+    );
+}
+
+/// Draw text over a frame with the font of the animation, the top left corner of the
+/// text at `x`, `y`. `\n` in the text starts a new line.
+fn show_text(from: &str, x: &str, y: &str, text: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let (x, y) = match (x.parse::<usize>(), y.parse::<usize>()) {
+        (Ok(x), Ok(y)) => (x, y),
+        _ => {
+            eprintln!("Invalid position {},{}: expected pixel coordinates", x, y);
+            std::process::exit(1);
+        }
+    };
+    let mut frame = from
+        .parse::<Frame>()
+        .and_then(|frame| {
+            animation
+                .groups
+                .frame_index(&frame)
+                .ok_or_else(|| format!("Unknown frame: {}", frame))
+        })
+        .map(|index| animation.frames[index].clone())
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    animation.font.draw(
+        &mut frame,
+        animation.num_cols,
+        x,
+        y,
+        &text.replace("\\n", "\n"),
+    );
+    println!("{}", oled::render(&frame, animation.num_cols));
+}
+
+/// Overlay two frames of an animation, outlining the regions of their diff
+fn show_diff(from: &str, to: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let frame = |name: &str| {
+        name.parse::<Frame>()
+            .and_then(|frame| {
+                animation
+                    .groups
+                    .frame_index(&frame)
+                    .ok_or_else(|| format!("Unknown frame: {}", frame))
+            })
+            .map(|index| &animation.frames[index])
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+    };
+    let (original, altered) = (frame(from), frame(to));
+    let regions = compression::Diff::from_original_and_altered(original, altered).regions();
+    println!(
+        "{}",
+        oled::render_diff(original, altered, animation.num_cols, &regions)
+    );
+    println!("██ unchanged  ++ added  -- removed  ·· unchanged within a region");
+    println!(
+        "{} regions, {} bytes: {}",
+        regions.len(),
+        regions
+            .iter()
+            .map(|(begin, end)| end - begin)
+            .sum::<usize>(),
+        regions
+            .iter()
+            .map(|(begin, end)| format!("{}..{}", begin, end))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+}
+
+/// Show which pixels and bytes change most across the frames,
+/// and write the heatmaps as images to `dir`
+fn show_heatmap(dir: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let heatmap = heatmap::Heatmap::new(&animation.frames, animation.num_cols, animation.num_rows);
+    println!(
+        "Pixels differing from the base frame\n{}",
+        heatmap.render_pixels()
+    );
+    println!(
+        "Bytes differing from the base frame\n{}",
+        heatmap.render_bytes()
+    );
+    println!(
+        "{} of {} bytes differ from the base frame in at least one frame",
+        heatmap.changing_bytes(),
+        heatmap.num_bytes()
+    );
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    std::fs::write(dir.join("heatmap_pixels.pgm"), heatmap.pixels_image())
+        .expect("Failed to write heatmap_pixels.pgm");
+    std::fs::write(dir.join("heatmap_bytes.pgm"), heatmap.bytes_image())
+        .expect("Failed to write heatmap_bytes.pgm");
+}
+
+/// Show how long each pixel is lit while playing the sequences back, flagging
+/// the ones at risk of burning in, with and without the pixel shift if any
+fn show_burn_in(manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    // Long enough to go through every offset of the pixel shifts
+    let duration = animation
+        .sequences
+        .iter()
+        .filter_map(|sequence| {
+            sequence
+                .pixel_shift()
+                .map(|pixel_shift| pixel_shift.cycle())
+        })
+        .fold(BURN_IN_DURATION, std::time::Duration::max);
+    let duty_cycle = |shifted| {
+        burn_in::DutyCycle::new(
+            &animation.frames,
+            &animation.sequences,
+            animation.num_cols,
+            animation.num_rows,
+            duration,
+            shifted,
+        )
+    };
+    let unshifted = duty_cycle(false);
+    println!(
+        "Share of the time each pixel is lit over {} s of each sequence\n{}",
+        duration.as_secs(),
+        unshifted.render()
+    );
+    print!("{}", unshifted.summary());
+    if animation
+        .sequences
+        .iter()
+        .any(|sequence| sequence.pixel_shift().is_some())
+    {
+        let shifted = duty_cycle(true);
+        println!("With the pixel shift\n{}", shifted.render());
+        print!("{}", shifted.summary());
+    }
+}
+
+/// Print the cost of the diff between every pair of frames, and compare
+/// encoding every frame against the first one to following the spanning tree
+fn show_similarity(manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let names = animation
+        .groups
+        .frames()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let width = names.iter().map(String::len).max().unwrap_or(0).max(6);
+    println!("Diff cost in bytes, from each row to each column");
+    println!(
+        "{:width$}{}",
+        "",
+        names
+            .iter()
+            .map(|name| format!(" {:>width$}", name, width = width))
+            .collect::<String>(),
+        width = width
+    );
+    for (name, row) in names
+        .iter()
+        .zip(compression::similarity_matrix(&animation.frames))
+    {
+        println!(
+            "{:width$}{}",
+            name,
+            row.iter()
+                .map(|cost| format!(" {:>width$}", cost, width = width))
+                .collect::<String>(),
+            width = width
+        );
+    }
+    println!("Minimum spanning tree");
+    for (name, parent) in names
+        .iter()
+        .zip(compression::spanning_tree(&animation.frames))
+    {
+        println!(
+            "  {} <- {}",
+            name,
+            parent.map_or("empty screen", |parent| names[parent].as_str())
+        );
+    }
+    let star = compression::compress_frames(&[("", &animation.groups, &animation.frames)]);
+    let tree = compression::compress_frames_as_tree(&animation.groups, &animation.frames);
+    for (index, frame) in animation.frames.iter().enumerate() {
+        if &tree.reconstruct_frame(index) != frame {
+            eprintln!("The tree encoding fails to decode {}", names[index]);
+            std::process::exit(1);
+        }
+    }
+    println!("Star encoding: {} bytes", star.size());
+    println!("Tree encoding: {} bytes", tree.size());
+}
+
+fn preview(path: &str) {
+    let animation = build_animation(&load_manifest(path));
+    display_bongo(
+        &|index| animation.frames[index].clone(),
+        &animation.sequences,
+        animation.num_cols,
+    );
+}
+
+/// Edit the frames of a manifest in the terminal, saving them back to their images.
+/// The frames are edited as drawn, before the manifest's transforms.
+fn edit(path: &str) {
+    let manifest = load_manifest(path);
+    let paths = manifest.frame_paths();
+    let mut editor = manifest
+        .load_frames()
+        .and_then(|frames| {
+            editor::Editor::new(
+                frames,
+                manifest.frame_names(),
+                manifest.display.width,
+                manifest.display.height,
+            )
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    editor.run(&|index, frame| manifest.save_frame(&paths[index], frame));
+    if editor.is_modified() {
+        eprintln!("Quit without saving some of the frames");
+    }
+}
+
+/// Play the sequences of a manifest back with its overlays drawn on top
+fn preview_composition(path: &str) {
+    let animation = build_animation(&load_manifest(path));
+    let layers = compose::render(&animation.overlays, &animation.font);
+    display_bongo(
+        &|index| compose::compose(&animation.frames[index], animation.num_cols, &layers),
+        &animation.sequences,
+        animation.num_cols,
+    );
+}
+
+/// Compress an animation with the encoding it asks for
+fn compress(animation: &manifest::Animation) -> compression::CompactAnimation {
+    match animation.encoding {
+        compression::Encoding::Star => {
+            compression::compress_frames(&[("", &animation.groups, &animation.frames)])
+        }
+        compression::Encoding::Tree => {
+            compression::compress_frames_as_tree(&animation.groups, &animation.frames)
+        }
+    }
+}
+
+/// The built-in bongo cat, or the animation described by a manifest or drawn
+/// in a text file (`.txt`)
+fn load_animation(manifest: Option<&str>) -> manifest::Animation {
+    match manifest {
+        Some(path) if path.ends_with(".txt") => std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|text| ascii::parse(&text))
+            .and_then(manifest::Animation::from_blocks)
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {}", path, e);
+                std::process::exit(1);
+            }),
+        Some(path) => build_animation(&load_manifest(path)),
+        None => {
+            let groups = fat_bongo::groups();
+            let frames = groups.frames().iter().map(fat_bongo::get_frame).collect();
+            manifest::Animation {
+                num_cols: NUM_COLS,
+                num_rows: NUM_ROWS,
+                sequences: bongo_sequences(&groups),
+                groups,
+                frames,
+                encoding: compression::Encoding::Star,
+                overlays: Vec::new(),
+                font: font::Font::default(),
+            }
+        }
+    }
+}
+
+/// The prefix of the names and files an exporter generates: the manifest's, or `bongo`
+fn export_prefix(manifest: Option<&str>) -> String {
+    match manifest {
+        Some(path) if !path.ends_with(".txt") => Some(load_manifest(path).output.prefix),
+        _ => None,
+    }
+    .filter(|prefix| !prefix.is_empty())
+    .unwrap_or_else(|| "bongo".to_string())
+}
+
+fn export_qmk(dir: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let prefix = export_prefix(manifest);
+    let slim = compress(&animation);
+    let module = qmk::export(
+        &prefix,
+        &slim,
+        &animation.groups,
+        &animation::Config::default(),
+        animation.num_cols,
+        animation.num_rows,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    let name = compression::c_identifier(&prefix);
+    std::fs::write(dir.join(format!("{}.h", name)), module.header).expect("Failed to write header");
+    std::fs::write(dir.join(format!("{}.c", name)), module.source).expect("Failed to write source");
+    std::fs::write(dir.join("rules.mk"), module.rules).expect("Failed to write rules.mk");
+}
+
+fn export_zmk(dir: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let prefix = export_prefix(manifest);
+    let widget = zmk::export(
+        &prefix,
+        &animation.frames,
+        &animation.groups,
+        &animation::Config::default(),
+        animation.num_cols,
+        animation.num_rows,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    let name = compression::c_identifier(&prefix);
+    std::fs::write(dir.join(format!("{}_images.c", name)), widget.images)
+        .expect("Failed to write images");
+    std::fs::write(dir.join(format!("{}_widget.h", name)), widget.header)
+        .expect("Failed to write widget header");
+    std::fs::write(dir.join(format!("{}_widget.c", name)), widget.source)
+        .expect("Failed to write widget source");
+    std::fs::write(dir.join("CMakeLists.txt"), widget.cmake)
+        .expect("Failed to write CMakeLists.txt");
+}
+
+fn export_kmk(dir: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let prefix = export_prefix(manifest);
+    let slim = compress(&animation);
+    let module = kmk::export(
+        &prefix,
+        &slim,
+        &animation.frames,
+        animation.num_cols,
+        animation.num_rows,
+    );
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    let name = compression::c_identifier(&prefix);
+    std::fs::write(dir.join(format!("{}.py", name)), module.python)
+        .expect("Failed to write module");
+    std::fs::write(dir.join(format!("{}.bmp", name)), module.sprite_sheet)
+        .expect("Failed to write sprite sheet");
+}
+
+fn export_u8g2(dir: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let prefix = export_prefix(manifest);
+    let bitmaps = u8g2::export(
+        &prefix,
+        &animation.frames,
+        &animation.groups,
+        animation.num_cols,
+        animation.num_rows,
+    );
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    for (name, xbm) in bitmaps.xbm {
+        std::fs::write(dir.join(name), xbm).expect("Failed to write frame");
+    }
+    std::fs::write(
+        dir.join(format!("{}_u8g2.h", compression::c_identifier(&prefix))),
+        bitmaps.header,
+    )
+    .expect("Failed to write header");
+}
+
+/// Write a BDF or PSF font, or the built-in glcdfont, as a `glcdfont.c` for QMK and
+/// a Rust module to `dir`, and show its characters
+fn export_glcdfont(dir: &str, path: Option<&str>) {
+    let table = path
+        .map_or_else(
+            || Ok(font::Font::default()),
+            |path| font::Font::load(std::path::Path::new(path)),
+        )
+        .and_then(|font| glcdfont::Table::from_font(&font))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    std::fs::write(dir.join("glcdfont.c"), table.c_source()).expect("Failed to write glcdfont.c");
+    std::fs::write(dir.join("glcdfont.rs"), table.rust_declarations(""))
+        .expect("Failed to write glcdfont.rs");
+    println!("{}", table.preview());
+    println!(
+        "{}x8 pixel characters from {} to {}, {} bytes",
+        table.width,
+        table.start,
+        table.end,
+        table.data.len()
+    );
+}
+
+fn export_adafruit_gfx(path: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    let header = adafruit_gfx::export(
+        &export_prefix(manifest),
+        &animation.frames,
+        &animation.groups,
+        animation.num_cols,
+        animation.num_rows,
+    );
+    std::fs::write(path, header).expect("Failed to write header");
+}
+
+fn export_embedded_graphics(representation: &str, path: &str, manifest: Option<&str>) {
+    let representation = match representation {
+        "raw" => embedded_graphics::Representation::Raw,
+        "compressed" => embedded_graphics::Representation::Compressed,
+        _ => {
+            eprintln!(
+                "Unknown representation {}: expected raw or compressed",
+                representation
+            );
+            std::process::exit(1);
+        }
+    };
+    let animation = load_animation(manifest);
+    let slim = compress(&animation);
+    let module = embedded_graphics::export(
+        representation,
+        &slim,
+        &animation.frames,
+        &animation.groups,
+        animation.num_cols,
+        animation.num_rows,
+    );
+    std::fs::write(path, module).expect("Failed to write module");
+}
+
+/// Apply transforms to every frame of a manifest, and write the transformed frames
+/// as PBM images to `dir`, along with a manifest describing them. The new manifest
+/// does not keep the comments of the original one
+fn transform_frames(dir: &str, path: &str, transforms: &[&str]) {
+    let manifest = load_manifest(path);
+    let manifest::Animation {
+        groups,
+        frames,
+        num_cols,
+        num_rows,
+        ..
+    } = build_animation(&manifest);
+    let (frames, num_cols, num_rows) = transforms
+        .iter()
+        .map(|transform| transform.parse::<transform::Transform>())
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|transforms| {
+            transform::apply_all(
+                &transforms,
+                frames,
+                num_cols,
+                num_rows,
+                std::path::Path::new(""),
+            )
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    for (frame, data) in groups.frames().iter().zip(frames.iter()) {
+        std::fs::write(
+            dir.join(format!("{}.pbm", frame)),
+            pbm::from_frame(data, num_cols, num_rows),
+        )
+        .expect("Failed to write frame");
+    }
+    let file_names = groups
+        .iter()
+        .map(|(name, _, len)| {
+            (0..len)
+                .map(|index| format!("{}.pbm", Frame::new(name, index)))
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    let text = manifest
+        .rewritten(dir, num_cols, num_rows, &file_names)
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .expect("The manifest is a file");
+    std::fs::write(dir.join(file_name), text).expect("Failed to write manifest");
+    eprintln!(
+        "Wrote {}: the comments of {} are not carried over",
+        dir.join(file_name).display(),
+        path
+    );
+}
+
+/// Draw every frame of an animation as text to `path`, named after the frame and
+/// with the duration it is first shown for
+fn export_ascii(path: &str, manifest: Option<&str>) {
+    let animation = load_animation(manifest);
+    // The built-in frames hold more rows than the display shows, keep them all
+    let num_rows = match animation.frames.first() {
+        Some(frame) if frame.len() > animation.num_cols * animation.num_rows.div_ceil(8) => {
+            oled::num_rows(frame, animation.num_cols)
+        }
+        _ => animation.num_rows,
+    };
+    let blocks = animation
+        .groups
+        .frames()
+        .iter()
+        .zip(animation.frames.iter())
+        .enumerate()
+        .map(|(index, (frame, data))| ascii::Block {
+            name: frame.to_string(),
+            duration: animation
+                .sequences
+                .iter()
+                .find_map(|sequence| sequence.frame_duration(index)),
+            num_cols: animation.num_cols,
+            num_rows,
+            frame: data.clone(),
+        })
+        .collect::<Vec<_>>();
+    std::fs::write(path, ascii::write(&blocks)).expect("Failed to write frames");
+}
+
+/// Write the built-in animation as PBM images and a manifest describing them
+fn export_pbm(dir: &str) {
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    let groups = fat_bongo::groups();
+    for frame in groups.frames().iter() {
+        let data = fat_bongo::get_frame(frame);
+        let image = pbm::from_frame(&data, NUM_COLS, oled::num_rows(&data, NUM_COLS));
+        std::fs::write(dir.join(format!("{}.pbm", frame)), image).expect("Failed to write frame");
+    }
+    let group = |(name, _, len): (&str, usize, usize)| {
+        let (duration, playback) = match name {
+            "prep" => (IDLE_FRAME_DURATION, "once"),
+            "tap" => (TAP_FRAME_DURATION, "ping-pong"),
+            _ => (IDLE_FRAME_DURATION, "loop"),
+        };
+        format!(
+            "[[group]]\nname = \"{}\"\nframes = [{}]\nduration_ms = {}\nplayback = \"{}\"\n",
+            name,
+            (0..len)
+                .map(|index| format!("\"{}.pbm\"", Frame::new(name, index)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            duration.as_millis(),
+            playback
+        )
+    };
+    let frame = fat_bongo::get_frame(&groups.frames()[0]);
+    let manifest = std::iter::once(format!(
+        "[display]\nwidth = {}\nheight = {}\n",
+        NUM_COLS,
+        oled::num_rows(&frame, NUM_COLS)
+    ))
+    .chain(groups.iter().map(group))
+    .chain(std::iter::once(
+        "[output]\nheader = \"bongo.h\"\nsource = \"bongo.c\"\nrust = \"bongo.rs\"\nprefix = \"bongo\"\n".to_string(),
+    ))
+    .collect::<Vec<_>>()
+    .join("\n");
+    std::fs::write(dir.join("bongo.toml"), manifest).expect("Failed to write manifest");
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.iter().skip(1).map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => demo(),
+        ["live"] => live::run(&slim_bongo::get_frame, &slim_bongo::groups(), NUM_COLS),
+        ["replay", log] => replay_keystroke_log(log),
+        ["build", manifest] => build(manifest),
+        ["preview", manifest] => preview(manifest),
+        ["edit", manifest] => edit(manifest),
+        ["compose", manifest] => preview_composition(manifest),
+        ["text", frame, x, y, text] => show_text(frame, x, y, text, None),
+        ["text", frame, x, y, text, manifest] => show_text(frame, x, y, text, Some(manifest)),
+        ["diff", from, to] => show_diff(from, to, None),
+        ["diff", from, to, manifest] => show_diff(from, to, Some(manifest)),
+        ["heatmap", dir] => show_heatmap(dir, None),
+        ["heatmap", dir, manifest] => show_heatmap(dir, Some(manifest)),
+        ["burn-in"] => show_burn_in(None),
+        ["burn-in", manifest] => show_burn_in(Some(manifest)),
+        ["similarity"] => show_similarity(None),
+        ["similarity", manifest] => show_similarity(Some(manifest)),
+        ["stats", mcu] => print_stats(mcu, None),
+        ["stats", mcu, manifest] => print_stats(mcu, Some(manifest)),
+        ["bundle", dir, ref manifests @ ..] if !manifests.is_empty() => bundle(dir, manifests),
+        ["transform", dir, manifest, ref transforms @ ..] if !transforms.is_empty() => {
+            transform_frames(dir, manifest, transforms)
+        }
+        ["ascii", path] => export_ascii(path, None),
+        ["ascii", path, manifest] => export_ascii(path, Some(manifest)),
+        ["export-pbm", dir] => export_pbm(dir),
+        ["qmk", dir] => export_qmk(dir, None),
+        ["qmk", dir, manifest] => export_qmk(dir, Some(manifest)),
+        ["zmk", dir] => export_zmk(dir, None),
+        ["zmk", dir, manifest] => export_zmk(dir, Some(manifest)),
+        ["kmk", dir] => export_kmk(dir, None),
+        ["kmk", dir, manifest] => export_kmk(dir, Some(manifest)),
+        ["u8g2", dir] => export_u8g2(dir, None),
+        ["u8g2", dir, manifest] => export_u8g2(dir, Some(manifest)),
+        ["glcdfont", dir] => export_glcdfont(dir, None),
+        ["glcdfont", dir, font] => export_glcdfont(dir, Some(font)),
+        ["adafruit-gfx", path] => export_adafruit_gfx(path, None),
+        ["adafruit-gfx", path, manifest] => export_adafruit_gfx(path, Some(manifest)),
+        ["embedded-graphics", representation, path] => {
+            export_embedded_graphics(representation, path, None)
+        }
+        ["embedded-graphics", representation, path, manifest] => {
+            export_embedded_graphics(representation, path, Some(manifest))
+        }
+        _ => {
+            eprintln!("Usage: {} [live | replay <keystroke log> | build <manifest> | preview <manifest> | edit <manifest> | compose <manifest> | bundle <dir> <manifest>... | stats <mcu|all> [manifest] | diff <frame> <frame> [manifest] | text <frame> <x> <y> <text> [manifest] | heatmap <dir> [manifest] | burn-in [manifest] | similarity [manifest] | transform <dir> <manifest> <transform>... | ascii <file> [manifest] | export-pbm <dir> | qmk <dir> [manifest] | zmk <dir> [manifest] | kmk <dir> [manifest] | u8g2 <dir> [manifest] | glcdfont <dir> [font] | adafruit-gfx <file> [manifest] | embedded-graphics <raw|compressed> <file> [manifest]]", args[0]);
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,500 @@
+use crate::ascii::{self, Block};
+use crate::compose::{Blend, Content, Layer};
+use crate::compression::Encoding;
+use crate::font::Font;
+use crate::frame::{Frame, Groups};
+use crate::image;
+use crate::oled::Bitmap;
+use crate::pbm;
+use crate::sequence::{PixelShift, Playback, Sequence};
+use crate::transform::{self, Transform};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// Describes an animation as data: the display it targets, its named groups of
+/// frames with their source images and timings, and where to write the output.
+///
+/// ```toml
+/// encoding = "tree"
+/// transforms = ["translate:-3,0", "invert"]
+/// font = "6x10.bdf"
+///
+/// [display]
+/// width = 128
+/// height = 32
+///
+/// [[group]]
+/// name = "idle"
+/// frames = ["idle_0.pbm", "idle_1.pbm"]
+/// duration_ms = 200
+/// playback = "loop"
+///
+/// [[group]]
+/// name = "tap"
+/// frames = ["tap_0.pbm", "tap_1.pbm"]
+/// durations_ms = [100, 150]
+/// playback = { repeat = 3 }
+///
+/// [pixel_shift]
+/// period_ms = 60000
+///
+/// [[overlay]]
+/// name = "wpm"
+/// x = 110
+/// y = 0
+/// text = "120"
+/// blend = "replace"
+///
+/// [[overlay]]
+/// name = "caps"
+/// x = 120
+/// y = 24
+/// icon = "caps.pbm"
+///
+/// [output]
+/// overlays = "bongo_overlays.h"
+/// header = "bongo.h"
+/// source = "bongo.c"
+/// rust = "bongo.rs"
+/// prefix = "bongo"
+/// ```
+///
+/// Frame images are either PBM files (`.pbm`), raw page-major bytes (`.bin`) or
+/// `#`/`.` text drawings (`.txt`) holding a single frame.
+/// Paths are relative to the manifest.
+/// The optional `encoding` is `star` (the default) or `tree`.
+/// Bundles of several manifests only take star encoded manifests without a `prefix`.
+/// The optional `transforms` are applied in order to every frame once loaded,
+/// images to combine with being relative to the manifest too.
+/// The optional `pixel_shift` moves every frame by up to one pixel every `period_ms`,
+/// to spread the wear of the pixels lit in every frame.
+/// Each optional `overlay` is drawn on top of every frame by the firmware: either text
+/// shown as `text` in previews, or a PBM `icon`.
+/// The optional `font`, a BDF or PSF file, sets the size of the text overlays and
+/// draws them in previews, instead of the 5x7 glcdfont.
+#[derive(Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub encoding: Encoding,
+    #[serde(default)]
+    pub transforms: Vec<String>,
+    pub display: Display,
+    #[serde(rename = "group")]
+    pub groups: Vec<Group>,
+    pub pixel_shift: Option<PixelShiftConfig>,
+    pub font: Option<PathBuf>,
+    #[serde(default, rename = "overlay")]
+    pub overlays: Vec<Overlay>,
+    #[serde(default)]
+    pub output: Output,
+    #[serde(skip)]
+    base_dir: PathBuf,
+    #[serde(skip)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+pub struct Display {
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Deserialize)]
+pub struct Group {
+    pub name: String,
+    pub frames: Vec<PathBuf>,
+    /// Duration of every frame of the group, unless overridden by `durations_ms`
+    #[serde(default = "default_duration_ms")]
+    pub duration_ms: u64,
+    /// Duration of each individual frame
+    #[serde(default)]
+    pub durations_ms: Vec<u64>,
+    #[serde(default = "default_playback")]
+    pub playback: Playback,
+}
+
+#[derive(Deserialize)]
+pub struct PixelShiftConfig {
+    /// How long each offset lasts, up to 65535 ms to fit the exported table
+    pub period_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct Overlay {
+    pub name: String,
+    pub x: usize,
+    pub y: usize,
+    #[serde(default = "default_blend")]
+    pub blend: Blend,
+    /// Text drawn by the firmware, shown as is in previews
+    pub text: Option<String>,
+    /// Number of characters reserved for the text, defaulting to its length
+    pub len: Option<usize>,
+    /// PBM image drawn as is
+    pub icon: Option<PathBuf>,
+}
+
+/// Where to write the generated files. Files without a path are not generated.
+#[derive(Deserialize, Default)]
+pub struct Output {
+    /// C header declaring the tables
+    pub header: Option<PathBuf>,
+    /// C source defining the tables, including the header by its file name
+    pub source: Option<PathBuf>,
+    /// Rust module holding the tables
+    pub rust: Option<PathBuf>,
+    /// C header locating the overlays, with the bitmaps of the icons
+    pub overlays: Option<PathBuf>,
+    /// Namespace prepended to every generated name, e.g. `bongo` for
+    /// `bongo_diff_bytes` and `BONGO_FRAME_SIZE`
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_duration_ms() -> u64 {
+    200
+}
+
+fn default_playback() -> Playback {
+    Playback::Loop
+}
+
+fn default_blend() -> Blend {
+    Blend::Or
+}
+
+/// The frames and sequences described by a manifest
+pub struct Animation {
+    pub num_cols: usize,
+    pub num_rows: usize,
+    pub groups: Groups,
+    pub frames: Vec<Vec<u8>>,
+    pub sequences: Vec<Sequence>,
+    pub encoding: Encoding,
+    pub overlays: Vec<Layer>,
+    /// Font of the text overlays
+    pub font: Font,
+}
+
+impl Animation {
+    /// The animation drawn as text blocks, each group played in a loop. Frames without
+    /// a duration last as long as in manifests.
+    pub fn from_blocks(blocks: Vec<Block>) -> Result<Self, String> {
+        let mut groups = Groups::new();
+        let mut sequences = Vec::new();
+        let mut offset = 0;
+        for (name, len) in ascii::groups(&blocks)? {
+            let steps = blocks[offset..offset + len]
+                .iter()
+                .enumerate()
+                .map(|(index, block)| {
+                    let duration = block
+                        .duration
+                        .unwrap_or(Duration::from_millis(default_duration_ms()));
+                    (offset + index, duration)
+                })
+                .collect::<Vec<_>>();
+            sequences.push(Sequence::new(&name).with_section(Playback::Loop, &steps));
+            groups = groups.with_group(&name, len);
+            offset += len;
+        }
+        Ok(Self {
+            num_cols: blocks[0].num_cols,
+            num_rows: blocks[0].num_rows,
+            groups,
+            frames: blocks.into_iter().map(|block| block.frame).collect(),
+            sequences,
+            encoding: Encoding::Star,
+            overlays: Vec::new(),
+            font: Font::default(),
+        })
+    }
+}
+
+/// Path to `path` from the directory `dir`, both being absolute
+fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let common = dir
+        .components()
+        .zip(path.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    dir.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut manifest: Self = toml::from_str(&text)
+            .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+        manifest.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        manifest.text = text;
+        Ok(manifest)
+    }
+    pub fn frame_len(&self) -> usize {
+        self.display.width * self.display.height.div_ceil(8)
+    }
+    /// Resolve one of the output paths relative to the manifest
+    pub fn output_path(&self, path: &Option<PathBuf>) -> Option<PathBuf> {
+        path.as_ref().map(|path| self.base_dir.join(path))
+    }
+    fn load_frame(&self, path: &Path) -> Result<Vec<u8>, String> {
+        image::read_frame(
+            &self.base_dir.join(path),
+            self.display.width,
+            self.frame_len(),
+        )
+    }
+    fn load_overlay(&self, overlay: &Overlay) -> Result<Layer, String> {
+        let content = match (&overlay.text, &overlay.icon) {
+            (Some(text), None) => Content::Text {
+                preview: text.clone(),
+                len: overlay.len.unwrap_or(text.chars().count()),
+            },
+            (None, Some(path)) => {
+                let path = self.base_dir.join(path);
+                let data = std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                let (width, height) =
+                    pbm::size(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+                let mut bitmap = Bitmap::new(width, height);
+                bitmap.data = pbm::to_frame(&data, width, bitmap.data.len())
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                Content::Icon(bitmap)
+            }
+            _ => {
+                return Err(format!(
+                    "Overlay {}: expected either text or an icon",
+                    overlay.name
+                ))
+            }
+        };
+        Ok(Layer {
+            name: overlay.name.clone(),
+            x: overlay.x,
+            y: overlay.y,
+            blend: overlay.blend,
+            content,
+        })
+    }
+    /// Paths of all the frame images, in group order, relative to the current directory
+    pub fn frame_paths(&self) -> Vec<PathBuf> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.frames.iter().map(|path| self.base_dir.join(path)))
+            .collect()
+    }
+    /// Names of all the frames, `{group}_{index}` in group order
+    pub fn frame_names(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                (0..group.frames.len()).map(move |index| Frame::new(&group.name, index).to_string())
+            })
+            .collect()
+    }
+    /// Load all the frame images, in group order, as drawn: before any transform
+    pub fn load_frames(&self) -> Result<Vec<Vec<u8>>, String> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.frames.iter())
+            .map(|path| self.load_frame(path))
+            .collect()
+    }
+    /// Write a frame of the display's size back to its image, in the format given by its
+    /// extension
+    pub fn save_frame(&self, path: &Path, frame: &[u8]) -> Result<(), String> {
+        image::write_frame(path, frame, self.display.width, self.display.height)
+    }
+    /// The manifest describing frames of `num_cols` by `num_rows` pixels stored in
+    /// `file_names`, one list per group, without transforms as they were already applied.
+    /// The manifest is meant for `dir`: the font, icon and output paths are rewritten
+    /// relative to it. The manifest is serialized anew: its comments and layout are not kept
+    pub fn rewritten(
+        &self,
+        dir: &Path,
+        num_cols: usize,
+        num_rows: usize,
+        file_names: &[Vec<String>],
+    ) -> Result<String, String> {
+        let mut table = self
+            .text
+            .parse::<toml::Table>()
+            .map_err(|e| format!("Invalid manifest: {}", e))?;
+        table.remove("transforms");
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))
+        };
+        let (base_dir, dir) = (
+            canonical(&Path::new(".").join(&self.base_dir))?,
+            canonical(dir)?,
+        );
+        let rebase = |value: &mut toml::Value| {
+            if let Some(path) = value.as_str() {
+                let path = relative_path(&dir, &base_dir.join(path));
+                *value = toml::Value::String(path.to_string_lossy().to_string());
+            }
+        };
+        if let Some(font) = table.get_mut("font") {
+            rebase(font);
+        }
+        for overlay in table
+            .get_mut("overlay")
+            .and_then(toml::Value::as_array_mut)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(icon) = overlay.get_mut("icon") {
+                rebase(icon);
+            }
+        }
+        if let Some(output) = table.get_mut("output").and_then(toml::Value::as_table_mut) {
+            for (_, path) in output
+                .iter_mut()
+                .filter(|(key, _)| ["header", "source", "rust", "overlays"].contains(&key.as_str()))
+            {
+                rebase(path);
+            }
+        }
+        if let Some(display) = table.get_mut("display").and_then(toml::Value::as_table_mut) {
+            display.insert("width".to_string(), toml::Value::Integer(num_cols as i64));
+            display.insert("height".to_string(), toml::Value::Integer(num_rows as i64));
+        }
+        let groups = table
+            .get_mut("group")
+            .and_then(toml::Value::as_array_mut)
+            .into_iter()
+            .flatten();
+        for (group, file_names) in groups.zip(file_names.iter()) {
+            if let Some(group) = group.as_table_mut() {
+                group.insert(
+                    "frames".to_string(),
+                    toml::Value::Array(
+                        file_names
+                            .iter()
+                            .map(|name| toml::Value::String(name.clone()))
+                            .collect(),
+                    ),
+                );
+            }
+        }
+        toml::to_string(&table).map_err(|e| format!("Failed to write manifest: {}", e))
+    }
+    /// Load all the frames, in group order, and build one sequence per group
+    pub fn build(&self) -> Result<Animation, String> {
+        let pixel_shift = match &self.pixel_shift {
+            Some(PixelShiftConfig { period_ms }) if (1..=u16::MAX as u64).contains(period_ms) => {
+                Some(PixelShift {
+                    period: Duration::from_millis(*period_ms),
+                })
+            }
+            Some(PixelShiftConfig { period_ms }) => {
+                return Err(format!(
+                    "Invalid pixel shift period of {} ms: expected 1 to {} ms",
+                    period_ms,
+                    u16::MAX
+                ))
+            }
+            None => None,
+        };
+        let mut groups = Groups::new();
+        let mut frames = Vec::new();
+        let mut sequences = Vec::new();
+        for group in self.groups.iter() {
+            if !group.durations_ms.is_empty() && group.durations_ms.len() != group.frames.len() {
+                return Err(format!(
+                    "Group {}: {} durations for {} frames",
+                    group.name,
+                    group.durations_ms.len(),
+                    group.frames.len()
+                ));
+            }
+            if group.frames.is_empty() {
+                return Err(format!("Group {}: no frames", group.name));
+            }
+            let durations_ms = std::iter::once(&group.duration_ms).chain(group.durations_ms.iter());
+            if durations_ms
+                .clone()
+                .any(|duration_ms| !(1..=u16::MAX as u64).contains(duration_ms))
+            {
+                return Err(format!(
+                    "Group {}: frames must last from 1 to {} ms",
+                    group.name,
+                    u16::MAX
+                ));
+            }
+            if let Playback::Repeat(count) = group.playback {
+                if count > usize::from(u8::MAX) {
+                    return Err(format!(
+                        "Group {}: {} repeats, expected up to {}",
+                        group.name,
+                        count,
+                        u8::MAX
+                    ));
+                }
+            }
+            let steps = group
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(index, path)| {
+                    let duration = group.durations_ms.get(index).unwrap_or(&group.duration_ms);
+                    frames.push(self.load_frame(path)?);
+                    Ok((frames.len() - 1, Duration::from_millis(*duration)))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let sequence = Sequence::new(&group.name).with_section(group.playback, &steps);
+            sequences.push(match pixel_shift {
+                Some(pixel_shift) => sequence.with_pixel_shift(pixel_shift),
+                None => sequence,
+            });
+            groups = groups.with_group(&group.name, group.frames.len());
+        }
+        let transforms = self
+            .transforms
+            .iter()
+            .map(|transform| transform.parse::<Transform>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (frames, num_cols, num_rows) = transform::apply_all(
+            &transforms,
+            frames,
+            self.display.width,
+            self.display.height,
+            &self.base_dir,
+        )?;
+        let overlays = self
+            .overlays
+            .iter()
+            .map(|overlay| self.load_overlay(overlay))
+            .collect::<Result<Vec<_>, _>>()?;
+        let font = match &self.font {
+            Some(path) => Font::load(&self.base_dir.join(path))?,
+            None => Font::default(),
+        };
+        for layer in overlays.iter() {
+            let (width, height) = layer.size(&font);
+            if layer.x + width > num_cols || layer.y + height > num_rows {
+                return Err(format!(
+                    "Overlay {}: {}x{} pixels at {},{} do not fit on {}x{} frames",
+                    layer.name, width, height, layer.x, layer.y, num_cols, num_rows
+                ));
+            }
+        }
+        Ok(Animation {
+            num_cols,
+            num_rows,
+            groups,
+            frames,
+            sequences,
+            encoding: self.encoding,
+            overlays,
+            font,
+        })
+    }
+}
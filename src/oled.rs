@@ -1,4 +1,5 @@
 use itertools::*;
+use termion::color;
 
 /// Emulate an oled display in the command line:
 /// Take a slice of bytes where each byte represents 8 rows of the display
@@ -18,9 +19,129 @@ pub fn render(data: &[u8], num_cols: usize) -> String {
                     });
                     pixels.collect::<String>()
                 })
-                .intersperse("\n".to_string())
-                .collect::<String>()
+                .join("\n")
         })
         .map(|s| s + "\n")
         .collect()
 }
+
+/// Overlay two frames to show what changes from `original` to `altered`:
+/// pixels lit in both are drawn as `██`, added pixels as `++` (green) and
+/// removed ones as `--` (red). Unlit pixels of the bytes within `regions`
+/// (begin and end byte indices) are drawn as `··` on a grey background,
+/// outlining what a diff stores.
+pub fn render_diff(
+    original: &[u8],
+    altered: &[u8],
+    num_cols: usize,
+    regions: &[(usize, usize)],
+) -> String {
+    let num_rows = num_rows(original, num_cols).max(num_rows(altered, num_cols));
+    (0..num_rows)
+        .map(|y| {
+            let pixels = (0..num_cols)
+                .map(|x| {
+                    let byte = (y / 8) * num_cols + x;
+                    let in_region = regions
+                        .iter()
+                        .any(|(begin, end)| (*begin..*end).contains(&byte));
+                    let background = if in_region {
+                        color::Bg(color::AnsiValue::grayscale(6)).to_string()
+                    } else {
+                        color::Bg(color::Reset).to_string()
+                    };
+                    let glyph = match (
+                        pixel(original, num_cols, x, y),
+                        pixel(altered, num_cols, x, y),
+                    ) {
+                        (true, true) => format!("{}██", color::Fg(color::White)),
+                        (false, true) => format!("{}++", color::Fg(color::Green)),
+                        (true, false) => format!("{}--", color::Fg(color::Red)),
+                        (false, false) if in_region => format!("{}··", color::Fg(color::White)),
+                        (false, false) => "  ".to_string(),
+                    };
+                    background + &glyph
+                })
+                .collect::<String>();
+            format!(
+                "{}{}{}",
+                pixels,
+                color::Bg(color::Reset),
+                color::Fg(color::Reset)
+            )
+        })
+        .join("\n")
+}
+
+/// Whether the pixel at column `x`, row `y` is lit.
+/// Pixels outside of the data are reported as unlit.
+pub fn pixel(data: &[u8], num_cols: usize, x: usize, y: usize) -> bool {
+    x < num_cols
+        && data
+            .get((y / 8) * num_cols + x)
+            .is_some_and(|byte| byte & (1 << (y % 8)) != 0)
+}
+
+/// Light or clear the pixel at column `x`, row `y`.
+/// Pixels outside of the data are ignored.
+pub fn set_pixel(data: &mut [u8], num_cols: usize, x: usize, y: usize, on: bool) {
+    if x >= num_cols {
+        return;
+    }
+    if let Some(byte) = data.get_mut((y / 8) * num_cols + x) {
+        if on {
+            *byte |= 1 << (y % 8);
+        } else {
+            *byte &= !(1 << (y % 8));
+        }
+    }
+}
+
+/// Move every pixel `dx` columns to the right and `dy` rows down.
+/// Pixels moved out of the frame are dropped, and uncovered ones are unlit.
+pub fn shift(data: &[u8], num_cols: usize, dx: isize, dy: isize) -> Vec<u8> {
+    let mut shifted = vec![0; data.len()];
+    for y in 0..num_rows(data, num_cols) {
+        for x in 0..num_cols {
+            if pixel(data, num_cols, x, y) {
+                if let (Some(x), Some(y)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+                    set_pixel(&mut shifted, num_cols, x, y, true);
+                }
+            }
+        }
+    }
+    shifted
+}
+
+/// A page-major image of any size, e.g. an icon or a line of text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Bitmap {
+    /// A bitmap with every pixel unlit
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height.div_ceil(8)],
+        }
+    }
+    /// Whether the pixel at column `x`, row `y` is lit. Pixels outside of the bitmap are unlit.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        y < self.height && pixel(&self.data, self.width, x, y)
+    }
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        if y < self.height {
+            set_pixel(&mut self.data, self.width, x, y, on);
+        }
+    }
+}
+
+/// Number of pixel rows needed to display `data`
+pub fn num_rows(data: &[u8], num_cols: usize) -> usize {
+    data.len().div_ceil(num_cols) * 8
+}
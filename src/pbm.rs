@@ -0,0 +1,151 @@
+use crate::oled;
+
+/// Tokenize the header of a netpbm file, skipping whitespace and comments.
+/// Returns the tokens and the offset of the first byte after the header.
+fn header_tokens(data: &[u8], count: usize) -> Result<(Vec<String>, usize), String> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while tokens.len() < count {
+        match data.get(index) {
+            None => return Err("Truncated PBM header".to_string()),
+            Some(b'#') => {
+                while data.get(index).is_some_and(|byte| *byte != b'\n') {
+                    index += 1;
+                }
+            }
+            Some(byte) if byte.is_ascii_whitespace() => index += 1,
+            Some(_) => {
+                let begin = index;
+                while data
+                    .get(index)
+                    .is_some_and(|byte| !byte.is_ascii_whitespace())
+                {
+                    index += 1;
+                }
+                tokens.push(String::from_utf8_lossy(&data[begin..index]).to_string());
+            }
+        }
+    }
+    // A single whitespace separates the header from the raster
+    Ok((tokens, index + 1))
+}
+
+/// Decode a plain (P1) or raw (P4) PBM image into a frame of `frame_len` bytes
+/// where each byte represents 8 rows of the display.
+/// The image is clipped to the frame, and padded with unlit pixels.
+pub fn to_frame(data: &[u8], num_cols: usize, frame_len: usize) -> Result<Vec<u8>, String> {
+    let (tokens, raster_begin) = header_tokens(data, 3)?;
+    let (width, height) = dimensions(&tokens)?;
+    let pixels: Vec<bool> = match tokens[0].as_str() {
+        "P1" => data[raster_begin.min(data.len())..]
+            .iter()
+            .filter(|byte| **byte == b'0' || **byte == b'1')
+            .map(|byte| *byte == b'1')
+            .collect(),
+        "P4" => {
+            let row_len = width.div_ceil(8);
+            let raster = &data[raster_begin.min(data.len())..];
+            if raster.len() < row_len * height {
+                return Err("Truncated PBM raster".to_string());
+            }
+            raster
+                .chunks(row_len)
+                .flat_map(|row| (0..width).map(move |x| row[x / 8] & (0x80 >> (x % 8)) != 0))
+                .collect()
+        }
+        magic => return Err(format!("Unsupported PBM format: {}", magic)),
+    };
+    if pixels.len() < width * height {
+        return Err("Truncated PBM raster".to_string());
+    }
+    let mut frame = vec![0; frame_len];
+    for (index, on) in pixels.iter().take(width * height).enumerate() {
+        if index % width < num_cols {
+            oled::set_pixel(&mut frame, num_cols, index % width, index / width, *on);
+        }
+    }
+    Ok(frame)
+}
+
+/// Width and height of a PBM image
+pub fn size(data: &[u8]) -> Result<(usize, usize), String> {
+    let (tokens, _) = header_tokens(data, 3)?;
+    dimensions(&tokens)
+}
+
+/// Width and height from the header tokens, neither of which may be 0
+fn dimensions(tokens: &[String]) -> Result<(usize, usize), String> {
+    let parse = |token: &str| match token.parse::<usize>() {
+        Ok(0) => {
+            Err("Invalid PBM dimension 0: images are at least 1 pixel wide and high".to_string())
+        }
+        Ok(dimension) => Ok(dimension),
+        Err(e) => Err(format!("Invalid PBM dimension {}: {}", token, e)),
+    };
+    Ok((parse(&tokens[1])?, parse(&tokens[2])?))
+}
+
+/// Encode the top `num_rows` rows of a frame as a plain (P1) PBM image
+pub fn from_frame(frame: &[u8], num_cols: usize, num_rows: usize) -> String {
+    let raster = (0..num_rows)
+        .map(|y| {
+            (0..num_cols)
+                .map(|x| {
+                    if oled::pixel(frame, num_cols, x, y) {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("P1\n{} {}\n{}\n", num_cols, num_rows, raster)
+}
+
+/// Encode row-major values from 0 to `max` as a plain (P2) PGM image
+pub fn graymap(values: &[usize], width: usize, max: usize) -> String {
+    let raster = values
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "P2\n{} {}\n{}\n{}\n",
+        width,
+        values.len() / width,
+        max.max(1),
+        raster
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_truncated_raw_rasters() {
+        assert_eq!(
+            to_frame(b"P4\n16 4\n\xff", 16, 16),
+            Err("Truncated PBM raster".to_string())
+        );
+        let frame = to_frame(b"P4\n16 2\n\xff\x00\x00\x01", 16, 16).unwrap();
+        assert_eq!(&frame[..9], &[1, 1, 1, 1, 1, 1, 1, 1, 0]);
+        assert_eq!(frame[15], 2);
+    }
+
+    #[test]
+    fn rejects_empty_images() {
+        for data in [&b"P4\n0 0\n"[..], b"P1\n0 4\n", b"P4\n8 0\n"] {
+            assert!(to_frame(data, 16, 16).is_err());
+            assert!(size(data).is_err());
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::animation::Config;
+use crate::compression::{
+    c_identifier, c_macro_name, fmt_as_c_default_define, prefixed, with_prefix, CompactAnimation,
+};
+use crate::frame::Groups;
+
+/// The files making up a QMK user module
+pub struct Module {
+    pub header: String,
+    pub source: String,
+    pub rules: String,
+}
+
+const HEADER: &str = r#"#pragma once
+
+#include QMK_KEYBOARD_H
+
+/* Generated by bongo_light.
+ *
+ * To enable, add the content of rules.mk to your keymap's rules.mk, then:
+ *
+ *     bool process_record_user(uint16_t keycode, keyrecord_t *record) {
+ *         bongo_process_record(record);
+ *         return true;
+ *     }
+ *
+ *     bool oled_task_user(void) {
+ *         bongo_render();
+ *         return false;
+ *     }
+ */
+
+void bongo_process_record(keyrecord_t *record);
+void bongo_render(void);
+"#;
+
+const DECODER: &str = r#"static char bongo_frame_buffer[BONGO_FRAME_SIZE];
+
+static void bongo_apply_diff(uint16_t regions_begin, uint16_t regions_end, uint16_t bytes_begin) {
+    uint16_t byte_index = bytes_begin;
+    for (uint16_t region = regions_begin; region < regions_end; region += 2) {
+        uint16_t begin = pgm_read_word(&bongo_diff_regions[region]);
+        uint16_t end   = pgm_read_word(&bongo_diff_regions[region + 1]);
+        for (uint16_t index = begin; index < end; ++index) {
+            bongo_frame_buffer[index] = pgm_read_byte(&bongo_diff_bytes[byte_index++]);
+        }
+    }
+}
+
+static void bongo_decode_frame(uint8_t frame) {
+    // Every frame is stored as a diff to its parent: entry 0 for the first diff, itself
+    // a diff to an empty screen, or entry k + 1 for frame k. Apply the chain from the top.
+    uint8_t chain[sizeof(bongo_frame_parents)];
+    uint8_t depth = 0;
+    for (uint8_t entry = frame + 1; entry != 0; entry = pgm_read_byte(&bongo_frame_parents[entry - 1])) {
+        chain[depth++] = entry - 1;
+    }
+    memset(bongo_frame_buffer, 0, BONGO_FRAME_SIZE);
+    bongo_apply_diff(0, pgm_read_word(&bongo_diff_regions_boundaries[0]), 0);
+    while (depth > 0) {
+        uint8_t parent = chain[--depth];
+        bongo_apply_diff(pgm_read_word(&bongo_diff_regions_boundaries[parent]), pgm_read_word(&bongo_diff_regions_boundaries[parent + 1]), pgm_read_word(&bongo_diff_bytes_boundaries[parent]));
+    }
+}
+"#;
+
+const STATE_MACHINE: &str = r#"static uint32_t bongo_last_keypress = 0;
+static bool     bongo_has_keypress = false;
+static uint8_t  bongo_tap_index    = BONGO_TAP_GROUP_COUNT - 1;
+static uint8_t  bongo_current      = 0xFF;
+
+void bongo_process_record(keyrecord_t *record) {
+    if (record->event.pressed) {
+        bongo_last_keypress = timer_read32();
+        bongo_has_keypress  = true;
+        // Alternate paws on every keypress
+        bongo_tap_index = (bongo_tap_index + 1) % BONGO_TAP_GROUP_COUNT;
+    }
+}
+
+static uint8_t bongo_select_frame(void) {
+    uint8_t  wpm            = get_current_wpm();
+    uint32_t since_keypress = timer_elapsed32(bongo_last_keypress);
+    if (!bongo_has_keypress || since_keypress >= BONGO_IDLE_TIMEOUT || wpm < BONGO_PREP_WPM) {
+        return BONGO_IDLE_GROUP_OFFSET + (timer_read32() / BONGO_IDLE_FRAME_DURATION) % BONGO_IDLE_GROUP_COUNT;
+    }
+    if (wpm >= BONGO_TAP_WPM && since_keypress < BONGO_TAP_FRAME_DURATION) {
+        return BONGO_TAP_GROUP_OFFSET + bongo_tap_index;
+    }
+    return BONGO_PREP_GROUP_OFFSET;
+}
+
+void bongo_render(void) {
+    uint8_t frame = bongo_select_frame();
+    if (frame != bongo_current) {
+        bongo_decode_frame(frame);
+        bongo_current = frame;
+    }
+    oled_write_raw(bongo_frame_buffer, MIN(BONGO_FRAME_SIZE, OLED_MATRIX_SIZE));
+}
+"#;
+
+const RULES: &str = "# On QMK versions older than 0.14, use OLED_DRIVER_ENABLE = yes instead
+OLED_ENABLE = yes
+OLED_DRIVER = SSD1306
+WPM_ENABLE = yes
+SRC += bongo.c
+";
+
+/// Generate a QMK module rendering the animation on a `width`x`height` OLED,
+/// switching between the idle, prep and tap groups of `config` based on the WPM.
+/// Every name, and the files, are named after `prefix`.
+pub fn export(
+    prefix: &str,
+    slim: &CompactAnimation,
+    groups: &Groups,
+    config: &Config,
+    width: usize,
+    height: usize,
+) -> Result<Module, String> {
+    let roles = [
+        ("IDLE", &config.idle_group),
+        ("PREP", &config.prep_group),
+        ("TAP", &config.tap_group),
+    ];
+    for (_, group) in roles.iter() {
+        if groups.len(group) == 0 {
+            return Err(format!("The animation has no frame in group {}", group));
+        }
+    }
+    if slim.num_frames() > usize::from(u8::MAX) {
+        return Err(format!(
+            "The animation has {} frames: the QMK decoder indexes up to {} frames with a byte",
+            slim.num_frames(),
+            u8::MAX
+        ));
+    }
+    let role_macros = roles
+        .iter()
+        .flat_map(|(role, group)| {
+            ["OFFSET", "COUNT"].iter().map(move |field| {
+                format!(
+                    "#define {} {}\n",
+                    c_macro_name(&prefixed(prefix, &format!("{}_GROUP_{}", role, field))),
+                    c_macro_name(&prefixed(prefix, &format!("{}_FRAMES_{}", group, field)))
+                )
+            })
+        })
+        .collect::<String>();
+    let tuning = [
+        ("PREP_WPM", config.prep_wpm.round() as u128),
+        ("TAP_WPM", config.tap_wpm.round() as u128),
+        (
+            "IDLE_FRAME_DURATION",
+            config.idle_frame_duration.as_millis(),
+        ),
+        ("TAP_FRAME_DURATION", config.tap_frame_duration.as_millis()),
+        ("IDLE_TIMEOUT", config.idle_timeout.as_millis()),
+    ]
+    .iter()
+    .map(|(name, value)| fmt_as_c_default_define(&c_macro_name(&prefixed(prefix, name)), value))
+    .collect::<String>();
+    let file_name = c_identifier(prefix);
+    let source = [
+        format!(
+            "/* Generated by bongo_light. Do not edit. */\n\n#include \"{}.h\"\n#include <string.h>\n\n",
+            file_name
+        ),
+        "#if defined(OLED_ENABLE) || defined(OLED_DRIVER_ENABLE)\n\n".to_string(),
+        format!(
+            "#if OLED_DISPLAY_WIDTH != {0} || OLED_DISPLAY_HEIGHT != {1}\n#    error \"{2}.c was generated for a {0}x{1} display (OLED_DISPLAY_{0}X{1})\"\n#endif\n\n",
+            width, height, file_name
+        ),
+        slim.c_declarations(prefix),
+        "\n".to_string(),
+        role_macros,
+        "\n".to_string(),
+        tuning,
+        "\n".to_string(),
+        with_prefix(DECODER, prefix),
+        "\n".to_string(),
+        with_prefix(STATE_MACHINE, prefix),
+        "\n#endif\n".to_string(),
+    ]
+    .concat();
+    Ok(Module {
+        header: with_prefix(HEADER, prefix),
+        source,
+        rules: with_prefix(RULES, prefix),
+    })
+}
@@ -0,0 +1,445 @@
+use crate::compression::{
+    c_type_size, c_unsigned_type, fmt_as_c_extern_array, fmt_as_c_global_array, fmt_as_rust_array,
+    prefixed,
+};
+use serde::Deserialize;
+use std::time::Duration;
+
+const PLAYBACK_CODES: &str = "// Playback: 0 = once, 1 = repeat, 2 = loop, 3 = ping-pong";
+const PIXEL_SHIFT_CODES: &str =
+    "// Pixel shift: offsets are stored plus one, 0 = left or up, 1 = none, 2 = right or down";
+
+/// Name, Rust type, C type and content of a table describing a sequence
+type Table = (&'static str, &'static str, &'static str, Vec<usize>);
+
+/// Offsets cycled through by a pixel shift, in pixels to the right and down.
+/// Each offset is one pixel away from the previous one.
+pub const PIXEL_SHIFT_OFFSETS: [(i8, i8); 9] = [
+    (0, 0),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// How the frames of a section are played back
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Playback {
+    /// Play the frames once, in order
+    Once,
+    /// Play the frames in order, the given number of times
+    Repeat(usize),
+    /// Play the frames in order, forever
+    Loop,
+    /// Play the frames forward then backward, forever
+    PingPong,
+}
+
+impl Playback {
+    // Encoding of the playback mode in the exported tables
+    fn code(&self) -> u8 {
+        match self {
+            Playback::Once => 0,
+            Playback::Repeat(_) => 1,
+            Playback::Loop => 2,
+            Playback::PingPong => 3,
+        }
+    }
+    fn repeat_count(&self) -> usize {
+        match self {
+            Playback::Repeat(count) => *count,
+            _ => 1,
+        }
+    }
+}
+
+/// Move the whole frame around its position over time, so that pixels lit in
+/// every frame are not always lit on the same spot of an OLED
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelShift {
+    /// How long each of `PIXEL_SHIFT_OFFSETS` lasts
+    pub period: Duration,
+}
+
+impl PixelShift {
+    /// Offset of the frame at `elapsed` since the start of the sequence
+    pub fn offset(&self, elapsed: Duration) -> (i8, i8) {
+        let index = elapsed.as_millis() / self.period.as_millis().max(1);
+        PIXEL_SHIFT_OFFSETS[index as usize % PIXEL_SHIFT_OFFSETS.len()]
+    }
+    /// Time to go through all the offsets
+    pub fn cycle(&self) -> Duration {
+        self.period * PIXEL_SHIFT_OFFSETS.len() as u32
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Step {
+    /// Index of the frame in the compressed tables
+    pub frame: usize,
+    pub duration: Duration,
+}
+
+struct Section {
+    playback: Playback,
+    steps: Vec<Step>,
+}
+
+impl Section {
+    /// The steps in playback order. A looping section whose steps take no time is
+    /// played only once, as time would otherwise never move past it.
+    fn play(&self) -> Box<dyn Iterator<Item = &Step> + '_> {
+        let timeless = self.steps.iter().all(|step| step.duration.is_zero());
+        match self.playback {
+            Playback::Once => Box::new(self.steps.iter()),
+            Playback::Repeat(count) => Box::new(std::iter::repeat_n(&self.steps, count).flatten()),
+            Playback::Loop if timeless => Box::new(self.steps.iter()),
+            Playback::Loop => Box::new(self.steps.iter().cycle()),
+            Playback::PingPong => {
+                // Don't show the first and last frames twice in a row when bouncing
+                let backward = self
+                    .steps
+                    .get(1..self.steps.len().saturating_sub(1))
+                    .unwrap_or(&[]);
+                let pass = self.steps.iter().chain(backward.iter().rev());
+                if timeless {
+                    Box::new(pass)
+                } else {
+                    Box::new(pass.cycle())
+                }
+            }
+        }
+    }
+}
+
+/// An ordered list of sections, each with its own playback mode.
+/// Sections after a `Loop` or `PingPong` section are never reached.
+pub struct Sequence {
+    name: String,
+    sections: Vec<Section>,
+    pixel_shift: Option<PixelShift>,
+}
+
+impl Sequence {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sections: Vec::new(),
+            pixel_shift: None,
+        }
+    }
+    pub fn with_section(mut self, playback: Playback, steps: &[(usize, Duration)]) -> Self {
+        self.sections.push(Section {
+            playback,
+            steps: steps
+                .iter()
+                .map(|(frame, duration)| Step {
+                    frame: *frame,
+                    duration: *duration,
+                })
+                .collect(),
+        });
+        self
+    }
+    pub fn with_pixel_shift(mut self, pixel_shift: PixelShift) -> Self {
+        self.pixel_shift = Some(pixel_shift);
+        self
+    }
+    pub fn pixel_shift(&self) -> Option<&PixelShift> {
+        self.pixel_shift.as_ref()
+    }
+    /// The sequence as part of `animation` in a bundle: named after the animation,
+    /// with each frame index replaced by its entry in the animation's index table
+    pub fn bundled(&self, animation: &str, frames: &[usize]) -> Self {
+        Self {
+            name: prefixed(animation, &self.name),
+            sections: self
+                .sections
+                .iter()
+                .map(|section| Section {
+                    playback: section.playback,
+                    steps: section
+                        .steps
+                        .iter()
+                        .map(|step| Step {
+                            frame: frames[step.frame],
+                            duration: step.duration,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            pixel_shift: self.pixel_shift,
+        }
+    }
+    /// All the steps in playback order. May be infinite.
+    pub fn play(&self) -> impl Iterator<Item = &Step> {
+        self.sections.iter().flat_map(Section::play)
+    }
+    /// The steps in playback order, stopping before `limit` is reached
+    pub fn play_for(&self, limit: Duration) -> impl Iterator<Item = &Step> {
+        let mut elapsed = Duration::from_secs(0);
+        self.play().take_while(move |step| {
+            elapsed += step.duration;
+            elapsed <= limit
+        })
+    }
+    /// How long `frame` is shown the first time it is played, if ever
+    pub fn frame_duration(&self, frame: usize) -> Option<Duration> {
+        self.steps()
+            .find(|step| step.frame == frame)
+            .map(|step| step.duration)
+    }
+    fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.steps.iter())
+    }
+    fn step_frames(&self) -> Vec<usize> {
+        self.steps().map(|step| step.frame).collect()
+    }
+    fn step_durations_ms(&self) -> Vec<u128> {
+        self.steps().map(|step| step.duration.as_millis()).collect()
+    }
+    fn section_boundaries(&self) -> Vec<usize> {
+        let mut index: usize = 0;
+        self.sections
+            .iter()
+            .map(|section| {
+                index += section.steps.len();
+                index
+            })
+            .collect()
+    }
+    fn section_playbacks(&self) -> Vec<u8> {
+        self.sections
+            .iter()
+            .map(|section| section.playback.code())
+            .collect()
+    }
+    fn section_repeats(&self) -> Vec<usize> {
+        self.sections
+            .iter()
+            .map(|section| section.playback.repeat_count())
+            .collect()
+    }
+}
+
+impl Sequence {
+    /// The tables describing the sequence, whose frames are indices into `num_frames` frames
+    fn tables(&self, num_frames: usize) -> Vec<Table> {
+        let to_usize = |values: Vec<u128>| values.into_iter().map(|value| value as usize).collect();
+        let mut tables = vec![
+            (
+                "sequence_frames",
+                "usize",
+                c_unsigned_type(num_frames.saturating_sub(1)),
+                self.step_frames(),
+            ),
+            (
+                "sequence_durations_ms",
+                "u16",
+                "uint16_t",
+                to_usize(self.step_durations_ms()),
+            ),
+            (
+                "sequence_sections_boundaries",
+                "usize",
+                "uint8_t",
+                self.section_boundaries(),
+            ),
+            (
+                "sequence_sections_playback",
+                "u8",
+                "uint8_t",
+                self.section_playbacks()
+                    .into_iter()
+                    .map(usize::from)
+                    .collect(),
+            ),
+            (
+                "sequence_sections_repeats",
+                "usize",
+                "uint8_t",
+                self.section_repeats(),
+            ),
+        ];
+        if let Some(pixel_shift) = &self.pixel_shift {
+            let offsets = |axis: fn(&(i8, i8)) -> i8| {
+                PIXEL_SHIFT_OFFSETS
+                    .iter()
+                    .map(|offset| (axis(offset) + 1) as usize)
+                    .collect()
+            };
+            tables.extend(vec![
+                (
+                    "sequence_shift_period_ms",
+                    "u16",
+                    "uint16_t",
+                    vec![pixel_shift.period.as_millis() as usize],
+                ),
+                ("sequence_shift_x", "u8", "uint8_t", offsets(|(x, _)| *x)),
+                ("sequence_shift_y", "u8", "uint8_t", offsets(|(_, y)| *y)),
+            ]);
+        }
+        tables
+    }
+    /// The tables of `tables`, once checked that every value fits in its C type
+    fn checked_tables(&self, num_frames: usize) -> Result<Vec<Table>, String> {
+        if let Some(step) = self.steps().find(|step| step.frame >= num_frames) {
+            return Err(format!(
+                "Sequence {}: frame {} is out of the {} frames",
+                self.name, step.frame, num_frames
+            ));
+        }
+        let tables = self.tables(num_frames);
+        for (table, _, c_type, values) in tables.iter() {
+            let max = match *c_type {
+                "uint8_t" => usize::from(u8::MAX),
+                "uint16_t" => usize::from(u16::MAX),
+                _ => u32::MAX as usize,
+            };
+            if let Some(value) = values.iter().find(|value| **value > max) {
+                return Err(format!(
+                    "Sequence {}: {} holds {}, over the {} of a {}",
+                    self.name, table, value, max, c_type
+                ));
+            }
+        }
+        Ok(tables)
+    }
+    /// Comments explaining the codes stored in the tables
+    fn codes(&self) -> Vec<String> {
+        std::iter::once(PLAYBACK_CODES)
+            .chain(self.pixel_shift.map(|_| PIXEL_SHIFT_CODES))
+            .map(str::to_string)
+            .collect()
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Number of bytes taken by the sequence tables in C, for an animation of `num_frames`
+    /// frames
+    pub fn size(&self, num_frames: usize) -> usize {
+        self.tables(num_frames)
+            .iter()
+            .map(|(_, _, c_type, values)| c_type_size(c_type) * values.len())
+            .sum()
+    }
+    /// The sequence tables as Rust constants, named after `prefix`
+    pub fn rust_declarations(&self, prefix: &str, num_frames: usize) -> Result<String, String> {
+        Ok(self
+            .codes()
+            .into_iter()
+            .chain(
+                self.checked_tables(num_frames)?
+                    .iter()
+                    .map(|(table, rust_type, _, values)| {
+                        fmt_as_rust_array(
+                            prefix,
+                            &format!("{}_{}", self.name, table),
+                            rust_type,
+                            values,
+                        )
+                    }),
+            )
+            .map(|line| format!("{}\n", line))
+            .collect())
+    }
+    /// Declarations of the sequence tables, for a header
+    pub fn c_header_declarations(&self, prefix: &str, num_frames: usize) -> Result<String, String> {
+        Ok(self
+            .codes()
+            .into_iter()
+            .chain(
+                self.checked_tables(num_frames)?
+                    .iter()
+                    .map(|(table, _, c_type, values)| {
+                        fmt_as_c_extern_array(
+                            prefix,
+                            &format!("{}_{}", self.name, table),
+                            c_type,
+                            values.len(),
+                        )
+                    }),
+            )
+            .map(|line| format!("{}\n", line))
+            .collect())
+    }
+    /// Definitions of the sequence tables declared by `c_header_declarations`
+    pub fn c_source_definitions(&self, prefix: &str, num_frames: usize) -> Result<String, String> {
+        Ok(self
+            .checked_tables(num_frames)?
+            .iter()
+            .map(|(table, _, c_type, values)| {
+                format!(
+                    "{}\n",
+                    fmt_as_c_global_array(
+                        prefix,
+                        &format!("{}_{}", self.name, table),
+                        c_type,
+                        values,
+                    )
+                )
+            })
+            .collect())
+    }
+}
+
+impl std::fmt::Display for Sequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Sequence {}: {} step(s) in {} section(s)",
+            self.name,
+            self.steps().count(),
+            self.sections.len()
+        )?;
+        match &self.pixel_shift {
+            Some(pixel_shift) => writeln!(
+                f,
+                ", shifted by up to 1 pixel every {} ms",
+                pixel_shift.period.as_millis()
+            ),
+            None => writeln!(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_for_ends_on_timeless_loops() {
+        let steps = [(0, Duration::from_millis(0)), (1, Duration::from_millis(0))];
+        for playback in [Playback::Loop, Playback::PingPong] {
+            let sequence = Sequence::new("idle").with_section(playback, &steps);
+            assert_eq!(sequence.play_for(Duration::from_secs(1)).count(), 2);
+        }
+        let sequence =
+            Sequence::new("idle").with_section(Playback::Loop, &[(0, Duration::from_millis(300))]);
+        assert_eq!(sequence.play_for(Duration::from_secs(1)).count(), 3);
+    }
+
+    #[test]
+    fn tables_reject_values_out_of_their_c_type() {
+        let sequence = Sequence::new("tap")
+            .with_section(Playback::Repeat(4), &[(300, Duration::from_millis(150))]);
+        assert!(sequence
+            .c_source_definitions("", 301)
+            .unwrap()
+            .contains("uint16_t"));
+        assert!(sequence.c_source_definitions("", 300).is_err());
+        let sequence =
+            Sequence::new("tap").with_section(Playback::Once, &[(0, Duration::from_millis(70000))]);
+        assert!(sequence.rust_declarations("", 1).is_err());
+        let sequence = Sequence::new("tap")
+            .with_section(Playback::Repeat(256), &[(0, Duration::from_millis(150))]);
+        assert!(sequence.c_header_declarations("", 1).is_err());
+    }
+}
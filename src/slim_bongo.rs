@@ -1,5 +1,5 @@
-use super::Frame;
 use crate::compression;
+use crate::frame::{Frame, Groups};
 const FRAME_SIZE: usize = 636;
 const DIFF_REGIONS_BOUNDARIES: [usize; 9] = [58, 58, 58, 84, 92, 100, 120, 152, 174];
 const DIFF_REGIONS: [usize; 174] = [
@@ -43,6 +43,8 @@ const DIFF_BYTES: [u8; 639] = [
     0, 1, 4, 4, 4, 4, 2, 2, 2, 1, 1, 122, 122, 121, 121, 121, 121, 57, 49, 136, 136, 135, 128, 48,
     120, 124, 254, 255, 63, 7, 255, 255, 127, 127, 63, 62, 28, 24,
 ];
+// Name, index of the first frame and number of frames
+const FRAME_GROUPS: [(&str, usize, usize); 3] = [("idle", 0, 5), ("prep", 5, 1), ("tap", 6, 2)];
 
 fn reconstruct_base_frame() -> Vec<u8> {
     let empty_screen = compression::CompactAnimation::construct_empty_frame(FRAME_SIZE);
@@ -78,11 +80,20 @@ fn reconstruct_frame(index: usize) -> Vec<u8> {
     diff.reconstruct_frame(&base_frame)
 }
 
+pub(super) fn groups() -> Groups {
+    FRAME_GROUPS
+        .iter()
+        .fold(Groups::new(), |groups, (name, _, len)| {
+            groups.with_group(name, *len)
+        })
+}
+
 pub(super) fn get_frame(frame: &Frame) -> Vec<u8> {
-    let index = match *frame {
-        Frame::Idle(index) => index,
-        Frame::Prep => 5,
-        Frame::Tap(index) => index + 6,
-    };
-    reconstruct_frame(index)
+    match FRAME_GROUPS
+        .iter()
+        .find(|(name, _, len)| *name == frame.group && frame.index < *len)
+    {
+        Some((_, offset, _)) => reconstruct_frame(offset + frame.index),
+        None => panic!("Unknown frame: {}", frame),
+    }
 }
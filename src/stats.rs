@@ -0,0 +1,228 @@
+use crate::compression::CompactAnimation;
+use crate::frame::Groups;
+use crate::sequence::Sequence;
+
+/// A microcontroller commonly found in keyboards.
+/// Figures are rough estimates, meant to tell whether an animation fits
+/// rather than to predict exact sizes and timings.
+pub struct McuProfile {
+    pub name: &'static str,
+    pub clock_mhz: usize,
+    /// Flash available to the firmware, excluding the bootloader
+    pub flash: usize,
+    pub ram: usize,
+    /// Flash and RAM used by a typical QMK build with OLED and WPM support
+    pub qmk_flash: usize,
+    pub qmk_ram: usize,
+    /// Cycles to clear one byte of the frame buffer
+    pub cycles_per_cleared_byte: usize,
+    /// Cycles to copy one byte of a diff from flash to the frame buffer
+    pub cycles_per_byte: usize,
+    /// Cycles to read the bounds of a region and start copying it
+    pub cycles_per_region: usize,
+}
+
+pub const MCU_PROFILES: [McuProfile; 4] = [
+    McuProfile {
+        name: "atmega32u4",
+        clock_mhz: 16,
+        // 32KB minus a 4KB bootloader
+        flash: 28672,
+        ram: 2560,
+        qmk_flash: 24000,
+        qmk_ram: 1700,
+        cycles_per_cleared_byte: 4,
+        cycles_per_byte: 12,
+        cycles_per_region: 40,
+    },
+    McuProfile {
+        name: "rp2040",
+        clock_mhz: 125,
+        // The usual 2MB external flash
+        flash: 2097152,
+        ram: 270336,
+        qmk_flash: 90000,
+        qmk_ram: 30000,
+        cycles_per_cleared_byte: 1,
+        cycles_per_byte: 6,
+        cycles_per_region: 30,
+    },
+    McuProfile {
+        name: "stm32f303",
+        clock_mhz: 72,
+        flash: 262144,
+        ram: 40960,
+        qmk_flash: 65000,
+        qmk_ram: 12000,
+        cycles_per_cleared_byte: 1,
+        cycles_per_byte: 5,
+        cycles_per_region: 25,
+    },
+    McuProfile {
+        name: "nrf52840",
+        clock_mhz: 64,
+        // 1MB minus the SoftDevice and bootloader
+        flash: 819200,
+        ram: 262144,
+        qmk_flash: 70000,
+        qmk_ram: 15000,
+        cycles_per_cleared_byte: 1,
+        cycles_per_byte: 5,
+        cycles_per_region: 25,
+    },
+];
+
+// Locals of the decoder and state of the animation,
+// besides the one byte per frame holding the chain of parents being decoded
+const DECODER_TEMPORARIES: usize = 16;
+
+// Warn when less than this share of a resource is left
+const HEADROOM_PERCENT: usize = 10;
+
+/// Find a profile by name, case insensitively
+pub fn profile(name: &str) -> Option<&'static McuProfile> {
+    MCU_PROFILES
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+impl McuProfile {
+    /// Estimated cycles to decode a frame whose diff, once added to the base
+    /// diff, spans `regions` regions and `bytes` bytes
+    fn decode_cycles(&self, frame_len: usize, regions: usize, bytes: usize) -> usize {
+        frame_len * self.cycles_per_cleared_byte
+            + bytes * self.cycles_per_byte
+            + regions * self.cycles_per_region
+    }
+    fn check(&self, resource: &str, used: usize, available: usize) -> Option<String> {
+        if used > available {
+            Some(format!(
+                "    warning: {} B over the {} alongside a typical QMK build\n",
+                used - available,
+                resource
+            ))
+        } else if (available - used) * 100 < available * HEADROOM_PERCENT {
+            Some(format!(
+                "    warning: less than {}% of the {} left alongside a typical QMK build\n",
+                HEADROOM_PERCENT, resource
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Break down the flash, RAM and decode time taken by an animation of `groups`,
+/// and check it against each of `profiles`
+pub fn report(
+    slim: &CompactAnimation,
+    groups: &Groups,
+    sequences: &[Sequence],
+    profiles: &[&McuProfile],
+) -> String {
+    let tables = slim.table_sizes();
+    let sequences_size = sequences
+        .iter()
+        .map(|sequence| sequence.size(slim.num_frames()))
+        .sum::<usize>();
+    let flash = slim.size() + sequences_size;
+    let diff_sizes = slim.diff_sizes();
+    let (base_regions, base_bytes) = diff_sizes[0];
+    let frames = &diff_sizes[1..];
+    let temporaries = DECODER_TEMPORARIES + frames.len();
+    let ram = slim.frame_len() + temporaries;
+    let diff_costs = slim.diff_costs();
+    let names = groups
+        .frames()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    let mut report = String::from("Flash\n");
+    for (name, size) in tables.iter() {
+        report += &format!("  {:<28}{:>8} B\n", name, size);
+    }
+    for sequence in sequences.iter() {
+        report += &format!(
+            "  {:<28}{:>8} B\n",
+            format!("{} sequence", sequence.name()),
+            sequence.size(slim.num_frames())
+        );
+    }
+    report += &format!("  {:<28}{:>8} B\n", "total", flash);
+
+    report += "Frames (diff regions, diff bytes, flash)\n";
+    report += &format!(
+        "  {:<16}{:>8}{:>8}{:>10} B\n",
+        "base", base_regions, base_bytes, diff_costs[0]
+    );
+    for (index, (name, (regions, bytes))) in names.iter().zip(frames).enumerate() {
+        report += &format!(
+            "  {:<16}{:>8}{:>8}{:>10} B\n",
+            name,
+            regions,
+            bytes,
+            diff_costs[index + 1]
+        );
+    }
+
+    report += "RAM\n";
+    report += &format!("  {:<28}{:>8} B\n", "frame buffer", slim.frame_len());
+    report += &format!("  {:<28}{:>8} B\n", "decoder temporaries", temporaries);
+    report += &format!("  {:<28}{:>8} B\n", "total", ram);
+
+    if base_bytes + frames.iter().map(|(_, bytes)| bytes).sum::<usize>() > u16::MAX as usize {
+        report += "warning: more diff bytes than 16 bit indices can address\n";
+    }
+
+    for profile in profiles {
+        // Frames apply the first diff, then the diffs along their chain of parents
+        let cycles = slim
+            .decode_sizes()
+            .iter()
+            .map(|(regions, bytes)| profile.decode_cycles(slim.frame_len(), *regions, *bytes))
+            .collect::<Vec<_>>();
+        report += &format!(
+            "{} ({} MHz, {} B flash, {} B RAM)\n",
+            profile.name, profile.clock_mhz, profile.flash, profile.ram
+        );
+        report += &format!(
+            "  flash: {} B + ~{} B for QMK = {} B ({}%)\n",
+            flash,
+            profile.qmk_flash,
+            flash + profile.qmk_flash,
+            100 * (flash + profile.qmk_flash) / profile.flash
+        );
+        report += &profile
+            .check("flash", flash + profile.qmk_flash, profile.flash)
+            .unwrap_or_default();
+        report += &format!(
+            "  RAM: {} B + ~{} B for QMK = {} B ({}%)\n",
+            ram,
+            profile.qmk_ram,
+            ram + profile.qmk_ram,
+            100 * (ram + profile.qmk_ram) / profile.ram
+        );
+        report += &profile
+            .check("RAM", ram + profile.qmk_ram, profile.ram)
+            .unwrap_or_default();
+        report += "  decode (cycles, us)\n";
+        for (name, cycles) in names.iter().zip(&cycles) {
+            report += &format!(
+                "    {:<14}{:>8}{:>8}\n",
+                name,
+                cycles,
+                cycles / profile.clock_mhz
+            );
+        }
+        if let Some((name, cycles)) = names.iter().zip(&cycles).max_by_key(|(_, cycles)| **cycles) {
+            report += &format!(
+                "  slowest frame: {}, ~{} cycles, ~{} us\n",
+                name,
+                cycles,
+                cycles / profile.clock_mhz
+            );
+        }
+    }
+    report
+}
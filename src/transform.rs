@@ -0,0 +1,255 @@
+use crate::image;
+use crate::oled;
+use std::path::{Path, PathBuf};
+
+/// How a frame is combined with another image, pixel by pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    And,
+    Or,
+    Xor,
+    /// Clear the pixels lit in the other image
+    Subtract,
+}
+
+impl Op {
+    fn apply(&self, byte: u8, other: u8) -> u8 {
+        match self {
+            Op::And => byte & other,
+            Op::Or => byte | other,
+            Op::Xor => byte ^ other,
+            Op::Subtract => byte & !other,
+        }
+    }
+}
+
+/// A change applied to every frame of an animation, possibly resizing it.
+///
+/// Transforms are written as their name followed by their arguments, e.g.
+/// `translate:-3,0`, `invert`, `crop:0,0,128,32`, `pad:0,0,0,8`, `flip-horizontal`,
+/// `flip-vertical`, `scale-2x` or `or:overlay.pbm` (also `and`, `xor` and `subtract`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transform {
+    /// Move the pixels right and down, dropping the ones moved out of the frame
+    Translate {
+        dx: isize,
+        dy: isize,
+    },
+    Invert,
+    /// Keep the rectangle with its top left corner at `x`, `y`
+    Crop {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// Add unlit pixels around the frame
+    Pad {
+        left: usize,
+        top: usize,
+        right: usize,
+        bottom: usize,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    /// Double the width and height, each pixel becoming a 2x2 square
+    Scale2x,
+    /// Combine with an image of the same size as the frame
+    Combine(Op, PathBuf),
+}
+
+/// Parse the `count` comma separated `arguments` of `transform`
+fn parse_arguments<T: std::str::FromStr>(
+    transform: &str,
+    arguments: &str,
+    count: usize,
+) -> Result<Vec<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    let arguments = arguments
+        .split(',')
+        .map(|argument| argument.trim().parse::<T>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid argument to {}: {}", transform, e))?;
+    if arguments.len() != count {
+        return Err(format!(
+            "Invalid transform {}: expected {} argument(s)",
+            transform, count
+        ));
+    }
+    Ok(arguments)
+}
+
+impl std::str::FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arguments) = s.split_once(':').unwrap_or((s, ""));
+        let combine = |op| {
+            if arguments.is_empty() {
+                Err(format!("Invalid transform {}: expected an image", s))
+            } else {
+                Ok(Transform::Combine(op, PathBuf::from(arguments)))
+            }
+        };
+        match name {
+            "translate" => {
+                let numbers = parse_arguments::<isize>(s, arguments, 2)?;
+                Ok(Transform::Translate {
+                    dx: numbers[0],
+                    dy: numbers[1],
+                })
+            }
+            "invert" => Ok(Transform::Invert),
+            "crop" => {
+                let sizes = parse_arguments::<usize>(s, arguments, 4)?;
+                Ok(Transform::Crop {
+                    x: sizes[0],
+                    y: sizes[1],
+                    width: sizes[2],
+                    height: sizes[3],
+                })
+            }
+            "pad" => {
+                let sizes = parse_arguments::<usize>(s, arguments, 4)?;
+                Ok(Transform::Pad {
+                    left: sizes[0],
+                    top: sizes[1],
+                    right: sizes[2],
+                    bottom: sizes[3],
+                })
+            }
+            "flip-horizontal" => Ok(Transform::FlipHorizontal),
+            "flip-vertical" => Ok(Transform::FlipVertical),
+            "scale-2x" => Ok(Transform::Scale2x),
+            "and" => combine(Op::And),
+            "or" => combine(Op::Or),
+            "xor" => combine(Op::Xor),
+            "subtract" => combine(Op::Subtract),
+            _ => Err(format!(
+                "Unknown transform {}: expected translate, invert, crop, pad, flip-horizontal, \
+                 flip-vertical, scale-2x, and, or, xor or subtract",
+                name
+            )),
+        }
+    }
+}
+
+/// Build a frame of `num_cols` by `num_rows` pixels, lighting each pixel whose
+/// source, if any, is lit in `frame`
+fn remap(
+    frame: &[u8],
+    num_cols: usize,
+    (new_cols, new_rows): (usize, usize),
+    source: &dyn Fn(usize, usize) -> Option<(usize, usize)>,
+) -> Vec<u8> {
+    let mut remapped = vec![0; new_cols * new_rows.div_ceil(8)];
+    for y in 0..new_rows {
+        for x in 0..new_cols {
+            if source(x, y).is_some_and(|(x, y)| oled::pixel(frame, num_cols, x, y)) {
+                oled::set_pixel(&mut remapped, new_cols, x, y, true);
+            }
+        }
+    }
+    remapped
+}
+
+impl Transform {
+    /// Size of the frames once transformed from `num_cols` by `num_rows` pixels
+    pub fn dimensions(&self, num_cols: usize, num_rows: usize) -> Result<(usize, usize), String> {
+        match self {
+            Transform::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                if *width == 0 || *height == 0 || x + width > num_cols || y + height > num_rows {
+                    return Err(format!(
+                        "Cannot crop {}x{} pixels at {},{} out of {}x{}",
+                        width, height, x, y, num_cols, num_rows
+                    ));
+                }
+                Ok((*width, *height))
+            }
+            Transform::Pad {
+                left,
+                top,
+                right,
+                bottom,
+            } => Ok((left + num_cols + right, top + num_rows + bottom)),
+            Transform::Scale2x => Ok((num_cols * 2, num_rows * 2)),
+            _ => Ok((num_cols, num_rows)),
+        }
+    }
+    /// Apply the transform to a frame of `num_cols` by `num_rows` pixels.
+    /// Paths of the images to combine with are relative to `base_dir`.
+    pub fn apply(
+        &self,
+        frame: &[u8],
+        num_cols: usize,
+        num_rows: usize,
+        base_dir: &Path,
+    ) -> Result<Vec<u8>, String> {
+        let dimensions = self.dimensions(num_cols, num_rows)?;
+        let remap = |source: &dyn Fn(usize, usize) -> Option<(usize, usize)>| {
+            Ok(remap(frame, num_cols, dimensions, source))
+        };
+        match self {
+            Transform::Translate { dx, dy } => remap(&|x, y| {
+                Some((x.checked_add_signed(-dx)?, y.checked_add_signed(-dy)?))
+                    .filter(|(x, y)| *x < num_cols && *y < num_rows)
+            }),
+            // Every pixel is remapped so that the padding bits of the last page stay unlit
+            Transform::Invert => {
+                let mut inverted = vec![0; frame.len()];
+                for y in 0..num_rows {
+                    for x in 0..num_cols {
+                        let on = oled::pixel(frame, num_cols, x, y);
+                        oled::set_pixel(&mut inverted, num_cols, x, y, !on);
+                    }
+                }
+                Ok(inverted)
+            }
+            Transform::Crop { x, y, .. } => remap(&|col, row| Some((col + x, row + y))),
+            Transform::Pad { left, top, .. } => remap(&|x, y| {
+                Some((x.checked_sub(*left)?, y.checked_sub(*top)?))
+                    .filter(|(x, y)| *x < num_cols && *y < num_rows)
+            }),
+            Transform::FlipHorizontal => remap(&|x, y| Some((num_cols - 1 - x, y))),
+            Transform::FlipVertical => remap(&|x, y| Some((x, num_rows - 1 - y))),
+            Transform::Scale2x => remap(&|x, y| Some((x / 2, y / 2))),
+            Transform::Combine(op, path) => {
+                let other = image::read_frame(&base_dir.join(path), num_cols, frame.len())?;
+                Ok(frame
+                    .iter()
+                    .zip(other.iter())
+                    .map(|(byte, other)| op.apply(*byte, *other))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Apply `transforms` in order to every frame of `num_cols` by `num_rows` pixels,
+/// returning the transformed frames and their size
+pub fn apply_all(
+    transforms: &[Transform],
+    frames: Vec<Vec<u8>>,
+    num_cols: usize,
+    num_rows: usize,
+    base_dir: &Path,
+) -> Result<(Vec<Vec<u8>>, usize, usize), String> {
+    transforms.iter().try_fold(
+        (frames, num_cols, num_rows),
+        |(frames, num_cols, num_rows), transform| {
+            let (new_cols, new_rows) = transform.dimensions(num_cols, num_rows)?;
+            let frames = frames
+                .iter()
+                .map(|frame| transform.apply(frame, num_cols, num_rows, base_dir))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((frames, new_cols, new_rows))
+        },
+    )
+}
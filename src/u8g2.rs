@@ -0,0 +1,121 @@
+use crate::compression::{
+    as_c_hex_array_string, c_group_defines, c_identifier, c_macro_name, prefixed, with_prefix,
+};
+use crate::frame::Groups;
+use crate::layout::Layout;
+
+/// The frames as standalone XBM images, and as a header for u8g2 and u8x8
+pub struct Bitmaps {
+    /// File name and content of one XBM image per frame
+    pub xbm: Vec<(String, String)>,
+    pub header: String,
+}
+
+const HEADER: &str = r#"/* Generated by bongo_light. Do not edit.
+ *
+ * Draw a frame with u8g2:
+ *
+ *     u8g2.drawXBMP(0, 0, BONGO_WIDTH, BONGO_HEIGHT, bongo_xbm[BONGO_IDLE_FRAMES_OFFSET]);
+ *
+ * or with u8x8, one row of tiles per page. drawTile reads from RAM, so copy
+ * each page out of flash first (memcpy_P on AVR):
+ *
+ *     uint8_t tiles[BONGO_WIDTH];
+ *     for (uint8_t page = 0; page < BONGO_PAGES; ++page) {
+ *         memcpy_P(tiles, bongo_tiles[BONGO_IDLE_FRAMES_OFFSET] + page * BONGO_WIDTH, BONGO_WIDTH);
+ *         u8x8.drawTile(0, page, BONGO_WIDTH / 8, tiles);
+ *     }
+ */
+
+#pragma once
+
+#include <stdint.h>
+
+#ifndef U8X8_PROGMEM
+#    define U8X8_PROGMEM
+#endif
+
+"#;
+
+/// Generate the XBM images and u8g2 header for every frame, named after `prefix`
+pub fn export(
+    prefix: &str,
+    frames: &[Vec<u8>],
+    groups: &Groups,
+    num_cols: usize,
+    num_rows: usize,
+) -> Bitmaps {
+    let names = groups
+        .frames()
+        .iter()
+        .map(|frame| c_identifier(&prefixed(prefix, &frame.to_string())))
+        .collect::<Vec<_>>();
+    let xbm = names
+        .iter()
+        .zip(frames.iter())
+        .map(|(name, frame)| {
+            (
+                format!("{}.xbm", name),
+                format!(
+                    "#define {0}_width {1}\n#define {0}_height {2}\nstatic unsigned char {0}_bits[] = {3};\n",
+                    name,
+                    num_cols,
+                    num_rows,
+                    as_c_hex_array_string(&Layout::RowLsbFirst.encode(frame, num_cols, num_rows))
+                ),
+            )
+        })
+        .collect();
+    let bitmaps = names
+        .iter()
+        .zip(frames.iter())
+        .map(|(name, frame)| {
+            format!(
+                "static const uint8_t {0}_xbm[] U8X8_PROGMEM = {1};\n\
+                 static const uint8_t {0}_tiles[] U8X8_PROGMEM = {2};\n\n",
+                name,
+                as_c_hex_array_string(&Layout::RowLsbFirst.encode(frame, num_cols, num_rows)),
+                as_c_hex_array_string(&Layout::PageVertical.encode(frame, num_cols, num_rows)),
+            )
+        })
+        .collect::<String>();
+    let pointers = |suffix: &str| {
+        format!(
+            "static const uint8_t *const {0}[] = {{{1}}};\n",
+            c_identifier(&prefixed(prefix, suffix)),
+            names
+                .iter()
+                .map(|name| format!("{}_{}", name, suffix))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    let header = [
+        with_prefix(HEADER, prefix),
+        [
+            ("WIDTH", num_cols),
+            ("HEIGHT", num_rows),
+            ("PAGES", num_rows.div_ceil(8)),
+        ]
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "#define {} {}\n",
+                c_macro_name(&prefixed(prefix, name)),
+                value
+            )
+        })
+        .collect::<String>()
+            + "\n",
+        c_group_defines(prefix, groups.iter())
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect(),
+        "\n".to_string(),
+        bitmaps,
+        pointers("xbm"),
+        pointers("tiles"),
+    ]
+    .concat();
+    Bitmaps { xbm, header }
+}
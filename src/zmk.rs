@@ -0,0 +1,213 @@
+use crate::animation::Config;
+use crate::compression::{
+    as_c_array_string, c_identifier, c_macro_name, fmt_as_c_default_define, prefixed, with_prefix,
+};
+use crate::frame::{Frame, Groups};
+use crate::layout::Layout;
+
+/// The files making up a ZMK display widget
+pub struct Widget {
+    pub images: String,
+    pub header: String,
+    pub source: String,
+    pub cmake: String,
+}
+
+// Colors of index 0 (unlit) and 1 (lit) of the 1 bit palette, as B, G, R, A
+const PALETTE: &str = r#"#if defined(BONGO_INVERTED)
+#    define BONGO_PALETTE 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0xff
+#else
+#    define BONGO_PALETTE 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff
+#endif
+"#;
+
+const HEADER: &str = r#"#pragma once
+
+#include <lvgl.h>
+#include <zephyr/kernel.h>
+
+/* Generated by bongo_light.
+ *
+ * Create the widget from your status screen:
+ *
+ *     static struct zmk_widget_bongo bongo_widget;
+ *     zmk_widget_bongo_init(&bongo_widget, screen);
+ *     lv_obj_align(zmk_widget_bongo_obj(&bongo_widget), LV_ALIGN_TOP_LEFT, 0, 0);
+ */
+
+struct zmk_widget_bongo {
+    sys_snode_t node;
+    lv_obj_t *obj;
+    int8_t state;
+};
+
+int zmk_widget_bongo_init(struct zmk_widget_bongo *widget, lv_obj_t *parent);
+lv_obj_t *zmk_widget_bongo_obj(struct zmk_widget_bongo *widget);
+"#;
+
+const WIDGET: &str = r#"static sys_slist_t widgets = SYS_SLIST_STATIC_INIT(&widgets);
+
+static void set_animation(lv_obj_t *animimg, const lv_img_dsc_t **frames, uint8_t count, uint32_t frame_duration) {
+    lv_animimg_set_src(animimg, (const void **)frames, count);
+    lv_animimg_set_duration(animimg, frame_duration * count);
+    lv_animimg_set_repeat_count(animimg, LV_ANIM_REPEAT_INFINITE);
+    lv_animimg_start(animimg);
+}
+
+static void update_animation(struct zmk_widget_bongo *widget, uint8_t wpm) {
+    int8_t state = wpm < BONGO_PREP_WPM ? 0 : wpm < BONGO_TAP_WPM ? 1 : 2;
+    if (state == widget->state) {
+        // Don't restart the animation when the speed changes within the same state
+        return;
+    }
+    widget->state = state;
+    if (state == 0) {
+        set_animation(widget->obj, bongo_idle_frames, ARRAY_SIZE(bongo_idle_frames), BONGO_IDLE_FRAME_DURATION);
+    } else if (state == 1) {
+        set_animation(widget->obj, bongo_prep_frames, ARRAY_SIZE(bongo_prep_frames), BONGO_IDLE_FRAME_DURATION);
+    } else {
+        set_animation(widget->obj, bongo_tap_frames, ARRAY_SIZE(bongo_tap_frames), BONGO_TAP_FRAME_DURATION);
+    }
+}
+
+struct bongo_state {
+    uint8_t wpm;
+};
+
+// Runs on the display thread, the only one allowed to touch LVGL objects
+static void bongo_update_cb(struct bongo_state state) {
+    struct zmk_widget_bongo *widget;
+    SYS_SLIST_FOR_EACH_CONTAINER(&widgets, widget, node) { update_animation(widget, state.wpm); }
+}
+
+static struct bongo_state bongo_get_state(const zmk_event_t *eh) {
+    return (struct bongo_state){.wpm = zmk_wpm_get_state()};
+}
+
+ZMK_DISPLAY_WIDGET_LISTENER(widget_bongo, struct bongo_state, bongo_update_cb, bongo_get_state)
+ZMK_SUBSCRIPTION(widget_bongo, zmk_wpm_state_changed);
+
+int zmk_widget_bongo_init(struct zmk_widget_bongo *widget, lv_obj_t *parent) {
+    widget->obj = lv_animimg_create(parent);
+    widget->state = -1;
+    update_animation(widget, 0);
+    sys_slist_append(&widgets, &widget->node);
+    widget_bongo_init();
+    return 0;
+}
+
+lv_obj_t *zmk_widget_bongo_obj(struct zmk_widget_bongo *widget) { return widget->obj; }
+"#;
+
+const CMAKE: &str = "target_sources(app PRIVATE bongo_images.c bongo_widget.c)\n";
+
+fn image_name(prefix: &str, frame: &Frame) -> String {
+    c_identifier(&prefixed(prefix, &frame.to_string()))
+}
+
+/// Convert every frame into an LVGL 1 bit indexed image
+fn images(
+    prefix: &str,
+    frames: &[Vec<u8>],
+    groups: &Groups,
+    num_cols: usize,
+    num_rows: usize,
+) -> String {
+    let stride = num_cols.div_ceil(8);
+    let images = groups
+        .frames()
+        .iter()
+        .zip(frames.iter())
+        .map(|(frame, data)| {
+            let name = image_name(prefix, frame);
+            format!(
+                "static const LV_ATTRIBUTE_MEM_ALIGN uint8_t {0}_map[] = {{\n    {5},\n    {1}\n}};\n\n\
+                 const lv_img_dsc_t {0} = {{\n    .header.cf = LV_IMG_CF_INDEXED_1BIT,\n    .header.always_zero = 0,\n    .header.reserved = 0,\n    \
+                 .header.w = {2},\n    .header.h = {3},\n    .data_size = {4},\n    .data = {0}_map,\n}};\n",
+                name,
+                as_c_array_string(&Layout::RowMsbFirst.encode(data, num_cols, num_rows))
+                    .trim_start_matches('{')
+                    .trim_end_matches('}'),
+                num_cols,
+                num_rows,
+                8 + stride * num_rows,
+                c_macro_name(&prefixed(prefix, "palette")),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "/* Generated by bongo_light. Do not edit. */\n\n#include <lvgl.h>\n\n#ifndef LV_ATTRIBUTE_MEM_ALIGN\n#    define LV_ATTRIBUTE_MEM_ALIGN\n#endif\n\n{}\n{}",
+        with_prefix(PALETTE, prefix),
+        images
+    )
+}
+
+/// Generate a ZMK widget playing the animation with an LVGL animated image,
+/// switching between the idle, prep and tap groups of `config` based on the WPM.
+/// Every name, and the files, are named after `prefix`.
+pub fn export(
+    prefix: &str,
+    frames: &[Vec<u8>],
+    groups: &Groups,
+    config: &Config,
+    num_cols: usize,
+    num_rows: usize,
+) -> Result<Widget, String> {
+    let roles = [
+        ("idle", &config.idle_group),
+        ("prep", &config.prep_group),
+        ("tap", &config.tap_group),
+    ];
+    let frame_lists = roles
+        .iter()
+        .map(|(role, group)| {
+            let names = (0..groups.len(group))
+                .map(|index| format!("&{}", image_name(prefix, &Frame::new(group, index))))
+                .collect::<Vec<_>>();
+            if names.is_empty() {
+                return Err(format!("The animation has no frame in group {}", group));
+            }
+            Ok(format!(
+                "{}static const lv_img_dsc_t *{}[] = {{{}}};\n",
+                names
+                    .iter()
+                    .map(|name| format!("extern const lv_img_dsc_t {};\n", &name[1..]))
+                    .collect::<String>(),
+                c_identifier(&prefixed(prefix, &format!("{}_frames", role))),
+                names.join(", ")
+            ))
+        })
+        .collect::<Result<String, String>>()?;
+    let tuning = [
+        ("PREP_WPM", config.prep_wpm.round() as u128),
+        ("TAP_WPM", config.tap_wpm.round() as u128),
+        (
+            "IDLE_FRAME_DURATION",
+            config.idle_frame_duration.as_millis(),
+        ),
+        ("TAP_FRAME_DURATION", config.tap_frame_duration.as_millis()),
+    ]
+    .iter()
+    .map(|(name, value)| fmt_as_c_default_define(&c_macro_name(&prefixed(prefix, name)), value))
+    .collect::<String>();
+    let source = [
+        &with_prefix(
+            "/* Generated by bongo_light. Do not edit. */\n\n#include \"bongo_widget.h\"\n\n",
+            prefix,
+        ),
+        "#include <zmk/display.h>\n#include <zmk/event_manager.h>\n#include <zmk/events/wpm_state_changed.h>\n#include <zmk/wpm.h>\n\n",
+        &tuning,
+        "\n",
+        &frame_lists,
+        "\n",
+        &with_prefix(WIDGET, prefix),
+    ]
+    .concat();
+    Ok(Widget {
+        images: images(prefix, frames, groups, num_cols, num_rows),
+        header: with_prefix(HEADER, prefix),
+        source,
+        cmake: with_prefix(CMAKE, prefix),
+    })
+}